@@ -0,0 +1,615 @@
+use crate::util;
+use anyhow::{bail, Result};
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Seek};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Component, Path, PathBuf};
+
+/// How a file, or the tar archive it holds, is compressed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Xz,
+    Bzip2,
+    Zstd,
+}
+
+impl Compression {
+    /// Detects the compression from the magic bytes at the start of a file.
+    fn from_magic(bytes: &[u8]) -> Self {
+        if bytes.starts_with(&[0x1f, 0x8b]) {
+            Compression::Gzip
+        } else if bytes.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            Compression::Xz
+        } else if bytes.starts_with(b"BZh") {
+            Compression::Bzip2
+        } else if bytes.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+
+    /// Wraps the reader in one that decompresses its content.
+    pub fn decoder<'a, R: Read + 'a>(self, reader: R) -> Result<Box<dyn Read + 'a>> {
+        Ok(match self {
+            Compression::None => Box::new(reader),
+            Compression::Gzip => Box::new(flate2::read::GzDecoder::new(reader)),
+            Compression::Xz => Box::new(xz2::read::XzDecoder::new_multi_decoder(reader)),
+            Compression::Bzip2 => Box::new(bzip2::read::MultiBzDecoder::new(reader)),
+            Compression::Zstd => Box::new(zstd::stream::read::Decoder::new(reader)?),
+        })
+    }
+}
+
+/// The format of a downloaded asset.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Zip,
+    /// A tar archive, e.g. `.tar.gz` or `.tgz`.
+    Tar(Compression),
+    /// A single compressed file, e.g. `.gz`.
+    File(Compression),
+}
+
+/// Suffixes of file names that tell their format. Others, e.g. `.gz`
+/// which may or may not hold a tar archive, are detected from content.
+const SUFFIXES: [(&str, Format); 11] = [
+    (".zip", Format::Zip),
+    (".tar", Format::Tar(Compression::None)),
+    (".tar.gz", Format::Tar(Compression::Gzip)),
+    (".tgz", Format::Tar(Compression::Gzip)),
+    (".tar.xz", Format::Tar(Compression::Xz)),
+    (".txz", Format::Tar(Compression::Xz)),
+    (".tar.bz2", Format::Tar(Compression::Bzip2)),
+    (".tbz2", Format::Tar(Compression::Bzip2)),
+    (".tbz", Format::Tar(Compression::Bzip2)),
+    (".tar.zst", Format::Tar(Compression::Zstd)),
+    (".tzst", Format::Tar(Compression::Zstd)),
+];
+
+/// Suffixes of compressed files, which may hold a tar archive.
+const COMPRESSED_SUFFIXES: [&str; 4] = [".gz", ".xz", ".bz2", ".zst"];
+
+/// Checks if the file name is the one of an archive
+/// or compressed file, e.g. a downloaded release asset.
+pub fn has_archive_suffix(name: &str) -> bool {
+    let name = name.to_lowercase();
+    SUFFIXES.iter().any(|(suffix, _)| name.ends_with(suffix))
+        || COMPRESSED_SUFFIXES
+            .iter()
+            .any(|suffix| name.ends_with(suffix))
+}
+
+impl Format {
+    /// Gives the format of the file at `path`, by the suffix of its name
+    /// or else from the magic bytes of its content.
+    pub fn of(path: &Path) -> Result<Self> {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        if let Some((_, format)) = SUFFIXES.iter().find(|(suffix, _)| name.ends_with(suffix)) {
+            return Ok(*format);
+        }
+
+        let mut magic = Vec::new();
+        fs::File::open(path)?.take(512).read_to_end(&mut magic)?;
+        if magic.starts_with(b"PK\x03\x04") || magic.starts_with(b"PK\x05\x06") {
+            return Ok(Format::Zip);
+        }
+
+        // A compressed file is a tar archive if its content is one.
+        let compression = Compression::from_magic(&magic);
+        let mut header = Vec::new();
+        compression
+            .decoder(fs::File::open(path)?)?
+            .take(512)
+            .read_to_end(&mut header)?;
+        if header.len() >= 262 && &header[257..262] == b"ustar" {
+            return Ok(Format::Tar(compression));
+        }
+
+        match compression {
+            Compression::None => bail!("unable to identify archive type of {}", name),
+            compression => Ok(Format::File(compression)),
+        }
+    }
+}
+
+/// Limits on what an archive unpacks to, so that a decompression
+/// bomb fails rather than filling up the disk.
+#[derive(Clone, Copy)]
+pub struct Limits {
+    /// Total size in bytes of the unpacked files.
+    pub max_size: u64,
+    /// Number of entries, including directories and links.
+    pub max_entries: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_size: 2 * 1024 * 1024 * 1024,
+            max_entries: 100_000,
+        }
+    }
+}
+
+/// An entry of an archive that was refused, e.g. one
+/// with a path leading outside of the target directory.
+#[derive(Debug)]
+pub struct UnsafeEntryError {
+    pub entry: String,
+    pub reason: String,
+}
+
+impl fmt::Display for UnsafeEntryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "refusing to extract {}: {}", self.entry, self.reason)
+    }
+}
+
+impl std::error::Error for UnsafeEntryError {}
+
+fn reject<T>(entry: &str, reason: &str) -> Result<T> {
+    Err(UnsafeEntryError {
+        entry: entry.to_string(),
+        reason: reason.to_string(),
+    }
+    .into())
+}
+
+/// Keeps count of what has been unpacked, against the limits.
+struct Budget {
+    limits: Limits,
+    size: u64,
+    entries: usize,
+}
+
+impl Budget {
+    fn new(limits: Limits) -> Self {
+        Self {
+            limits,
+            size: 0,
+            entries: 0,
+        }
+    }
+
+    fn add_entry(&mut self, entry: &str) -> Result<()> {
+        self.entries += 1;
+        if self.entries > self.limits.max_entries {
+            let reason = format!("archive has more than {} entries", self.limits.max_entries);
+            return reject(entry, &reason);
+        }
+        Ok(())
+    }
+
+    fn add_size(&mut self, entry: &str, size: u64) -> Result<()> {
+        self.size = self.size.saturating_add(size);
+        if self.size > self.limits.max_size {
+            let reason = format!(
+                "archive unpacks to more than {}",
+                util::format_size(self.limits.max_size)
+            );
+            return reject(entry, &reason);
+        }
+        Ok(())
+    }
+}
+
+/// Extracts a tar archive to `outdir`.
+pub fn untar<R: Read>(reader: R, outdir: &Path, limits: Limits) -> Result<()> {
+    fs::create_dir_all(outdir)?;
+    let mut budget = Budget::new(limits);
+
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let name = String::from_utf8_lossy(&entry.path_bytes()).to_string();
+        budget.add_entry(&name)?;
+
+        let path = match entry_path(outdir, &name)? {
+            Some(path) => path,
+            None => continue,
+        };
+        check_parent(outdir, &path, &name)?;
+
+        match entry.header().entry_type() {
+            tar::EntryType::Symlink => {
+                let target = match entry.link_name()? {
+                    Some(target) => target.into_owned(),
+                    None => return reject(&name, "symlink without a target"),
+                };
+                check_link(outdir, &path, &name, &target)?;
+            }
+            tar::EntryType::Link => {
+                // Hard links are relative to the root of the archive.
+                let target = String::from_utf8_lossy(&entry.link_name_bytes().unwrap_or_default())
+                    .to_string();
+                if entry_path(outdir, &target)?.is_none() {
+                    return reject(&name, "hard link without a target");
+                }
+            }
+            _ => budget.add_size(&name, entry.size())?,
+        }
+
+        if !entry.unpack_in(outdir)? {
+            return reject(&name, "path leads outside of the target directory");
+        }
+    }
+
+    Ok(())
+}
+
+/// Extracts a zip archive to `outdir`.
+pub fn unzip<R: Read + Seek>(reader: R, outdir: &Path, limits: Limits) -> Result<()> {
+    fs::create_dir_all(outdir)?;
+    let mut budget = Budget::new(limits);
+
+    let mut archive = zip::ZipArchive::new(reader)?;
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        let name = file.name().to_string();
+        budget.add_entry(&name)?;
+
+        let path = match entry_path(outdir, &name)? {
+            Some(path) => path,
+            None => continue,
+        };
+
+        if file.is_dir() {
+            fs::create_dir_all(&path)?;
+            check_parent(outdir, &path, &name)?;
+            continue;
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        check_parent(outdir, &path, &name)?;
+
+        let mode = file.unix_mode();
+        if mode.is_some_and(|mode| mode & 0o170000 == 0o120000) {
+            let mut target = String::new();
+            file.by_ref().take(4096).read_to_string(&mut target)?;
+            check_link(outdir, &path, &name, Path::new(&target))?;
+
+            if path.symlink_metadata().is_ok() {
+                fs::remove_file(&path)?;
+            }
+            std::os::unix::fs::symlink(&target, &path)?;
+            continue;
+        }
+
+        let size = file.size();
+        budget.add_size(&name, size)?;
+
+        let mut out = fs::File::create(&path)?;
+        let written = io::copy(&mut file.by_ref().take(size + 1), &mut out)?;
+        if written > size {
+            return reject(&name, "entry is larger than its declared size");
+        }
+
+        if let Some(mode) = mode {
+            fs::set_permissions(&path, fs::Permissions::from_mode(mode & 0o777))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Unpacks a single compressed file, e.g. of a `.gz`, to `outpath`
+/// within the size limit, streaming it rather than buffering it.
+pub fn unpack_file<R: Read>(reader: R, outpath: &Path, name: &str, limits: Limits) -> Result<()> {
+    let mut file = fs::File::create(outpath)?;
+    let size = io::copy(&mut reader.take(limits.max_size + 1), &mut file)?;
+    if size > limits.max_size {
+        drop(file);
+        fs::remove_file(outpath)?;
+        let reason = format!(
+            "file unpacks to more than {}",
+            util::format_size(limits.max_size)
+        );
+        return reject(name, &reason);
+    }
+    Ok(())
+}
+
+/// Gives the path within `outdir` of an entry, refusing absolute paths
+/// and `..`. Entries of the directory itself, e.g. `./`, give None.
+fn entry_path(outdir: &Path, name: &str) -> Result<Option<PathBuf>> {
+    let mut path = PathBuf::new();
+    for component in Path::new(name).components() {
+        match component {
+            Component::Normal(part) => path.push(part),
+            Component::CurDir => {}
+            Component::ParentDir => return reject(name, "path contains .."),
+            Component::RootDir | Component::Prefix(_) => return reject(name, "path is absolute"),
+        }
+    }
+
+    if path.as_os_str().is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(outdir.join(path)))
+    }
+}
+
+/// Checks that the parent directory of an entry, which may have
+/// been unpacked as a symlink, is within `outdir`.
+fn check_parent(outdir: &Path, path: &Path, name: &str) -> Result<()> {
+    let parent = match path.parent() {
+        Some(parent) if parent.exists() => parent,
+        _ => return Ok(()),
+    };
+
+    if !fs::canonicalize(parent)?.starts_with(fs::canonicalize(outdir)?) {
+        return reject(
+            name,
+            "path leads outside of the target directory through a symlink",
+        );
+    }
+    Ok(())
+}
+
+/// Checks that a symlink at `path` to `target` points within `outdir`. Only
+/// leading `..` are allowed in targets, e.g. `../lib/libfoo.so`, so that they
+/// can't walk out through other symlinks.
+fn check_link(outdir: &Path, path: &Path, name: &str, target: &Path) -> Result<()> {
+    let root = fs::canonicalize(outdir)?;
+    let parent = path.parent().unwrap_or(outdir);
+    fs::create_dir_all(parent)?;
+
+    let mut resolved = fs::canonicalize(parent)?;
+    let mut descended = false;
+    for component in target.components() {
+        match component {
+            Component::Normal(part) => {
+                descended = true;
+                resolved.push(part);
+            }
+            Component::CurDir => {}
+            Component::ParentDir if !descended => {
+                resolved.pop();
+                if !resolved.starts_with(&root) {
+                    let reason = format!(
+                        "symlink to {} points outside of the target directory",
+                        target.display()
+                    );
+                    return reject(name, &reason);
+                }
+            }
+            Component::ParentDir => {
+                let reason = format!("symlink to {} has .. after a directory", target.display());
+                return reject(name, &reason);
+            }
+            Component::RootDir | Component::Prefix(_) => {
+                let reason = format!("symlink to absolute path {}", target.display());
+                return reject(name, &reason);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+
+    /// Builds a tar of entries, of a name, type, link target and content,
+    /// writing the names as is, which the tar builder would refuse.
+    fn tar(entries: &[(&str, tar::EntryType, &str, &[u8])]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (name, kind, link, data) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+            header.as_old_mut().linkname[..link.len()].copy_from_slice(link.as_bytes());
+            header.set_entry_type(*kind);
+            header.set_mode(0o755);
+            header.set_size(data.len() as u64);
+            header.set_cksum();
+            builder.append(&header, *data).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    fn untar_err(archive: Vec<u8>, limits: Limits) -> UnsafeEntryError {
+        let dir = tempfile::tempdir_in(".").unwrap();
+        let err = untar(Cursor::new(archive), dir.path(), limits).unwrap_err();
+        err.downcast::<UnsafeEntryError>()
+            .expect("unsafe entry error")
+    }
+
+    #[test]
+    fn test_untar() -> Result<()> {
+        let archive = tar(&[
+            ("tool/bin/tool", tar::EntryType::Regular, "", b"#!/bin/sh\n"),
+            ("tool/lib", tar::EntryType::Symlink, "bin", b""),
+            ("tool/bin/up", tar::EntryType::Symlink, "../lib/tool", b""),
+        ]);
+
+        let dir = tempfile::tempdir_in(".")?;
+        untar(Cursor::new(archive), dir.path(), Limits::default())?;
+        assert!(util::is_executable(&dir.path().join("tool/bin/tool")));
+        assert!(dir.path().join("tool/bin/up").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_untar_unsafe() {
+        let regular = tar::EntryType::Regular;
+        let symlink = tar::EntryType::Symlink;
+
+        let err = untar_err(tar(&[("../evil", regular, "", b"x")]), Limits::default());
+        assert_eq!(err.entry, "../evil");
+
+        let err = untar_err(tar(&[("/etc/evil", regular, "", b"x")]), Limits::default());
+        assert_eq!(err.entry, "/etc/evil");
+
+        let err = untar_err(
+            tar(&[("a/link", symlink, "../../x", b"")]),
+            Limits::default(),
+        );
+        assert_eq!(err.entry, "a/link");
+
+        let err = untar_err(
+            tar(&[("link", symlink, "/etc/passwd", b"")]),
+            Limits::default(),
+        );
+        assert_eq!(err.entry, "link");
+
+        let archive = tar(&[
+            ("a/b/up", symlink, "..", b""),
+            ("link", symlink, "a/b/up/../../x", b""),
+        ]);
+        let err = untar_err(archive, Limits::default());
+        assert_eq!(err.entry, "link");
+    }
+
+    #[test]
+    fn test_untar_limits() {
+        let regular = tar::EntryType::Regular;
+        let archive = tar(&[("a", regular, "", &[0; 600]), ("b", regular, "", &[0; 600])]);
+        let limits = Limits {
+            max_size: 1000,
+            max_entries: 10,
+        };
+        assert_eq!(untar_err(archive.clone(), limits).entry, "b");
+
+        let limits = Limits {
+            max_size: 10_000,
+            max_entries: 1,
+        };
+        assert_eq!(untar_err(archive, limits).entry, "b");
+    }
+
+    #[test]
+    fn test_unzip() -> Result<()> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options = zip::write::SimpleFileOptions::default().unix_permissions(0o755);
+        writer.start_file("tool/tool", options)?;
+        writer.write_all(b"#!/bin/sh\n")?;
+        writer.add_symlink("tool/link", "tool", options)?;
+        let archive = writer.finish()?.into_inner();
+
+        let dir = tempfile::tempdir_in(".")?;
+        unzip(Cursor::new(archive), dir.path(), Limits::default())?;
+        assert!(util::is_executable(&dir.path().join("tool/tool")));
+        assert!(dir.path().join("tool/link").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_unzip_unsafe() -> Result<()> {
+        let options = zip::write::SimpleFileOptions::default();
+        for (name, link) in [("../evil", None), ("link", Some("../../etc"))] {
+            let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+            match link {
+                Some(target) => writer.add_symlink(name, target, options)?,
+                None => writer.start_file(name, options)?,
+            }
+            let archive = writer.finish()?.into_inner();
+
+            let dir = tempfile::tempdir_in(".")?;
+            let err = unzip(Cursor::new(archive), dir.path(), Limits::default()).unwrap_err();
+            let err = err
+                .downcast::<UnsafeEntryError>()
+                .expect("unsafe entry error");
+            assert_eq!(err.entry, name);
+        }
+        Ok(())
+    }
+
+    fn compress(compression: Compression, bytes: &[u8]) -> Vec<u8> {
+        match compression {
+            Compression::None => bytes.to_vec(),
+            Compression::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(bytes).unwrap();
+                encoder.finish().unwrap()
+            }
+            Compression::Xz => {
+                let mut encoder = xz2::write::XzEncoder::new(Vec::new(), 6);
+                encoder.write_all(bytes).unwrap();
+                encoder.finish().unwrap()
+            }
+            Compression::Bzip2 => {
+                let mut encoder =
+                    bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+                encoder.write_all(bytes).unwrap();
+                encoder.finish().unwrap()
+            }
+            Compression::Zstd => zstd::encode_all(bytes, 3).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_format_of() -> Result<()> {
+        let dir = tempfile::tempdir_in(".")?;
+        let tar = tar(&[("tool", tar::EntryType::Regular, "", b"#!/bin/sh\n")]);
+
+        let formats = [
+            ("tool-linux.tar.xz", Compression::Xz),
+            ("tool-linux.tbz2", Compression::Bzip2),
+            ("tool-linux.gz", Compression::Gzip),
+            ("tool-linux.xz", Compression::Xz),
+            ("tool-linux.bz2", Compression::Bzip2),
+            ("tool-linux.zst", Compression::Zstd),
+            ("tool-linux", Compression::None),
+        ];
+        for (name, compression) in formats {
+            let path = dir.path().join(name);
+            fs::write(&path, compress(compression, &tar))?;
+            assert_eq!(Format::of(&path)?, Format::Tar(compression), "{}", name);
+
+            let outdir = dir.path().join(format!("{}.out", name));
+            untar(
+                compression.decoder(fs::File::open(&path)?)?,
+                &outdir,
+                Limits::default(),
+            )?;
+            assert!(util::is_executable(&outdir.join("tool")), "{}", name);
+        }
+
+        let path = dir
+            .path()
+            .join("rust-analyzer-x86_64-unknown-linux-gnu.zst");
+        fs::write(&path, compress(Compression::Zstd, b"\x7fELF"))?;
+        assert_eq!(Format::of(&path)?, Format::File(Compression::Zstd));
+
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        writer.start_file("tool", zip::write::SimpleFileOptions::default())?;
+        let path = dir.path().join("tool-windows");
+        fs::write(&path, writer.finish()?.into_inner())?;
+        assert_eq!(Format::of(&path)?, Format::Zip);
+
+        let path = dir.path().join("tool.exe");
+        fs::write(&path, b"MZ")?;
+        assert!(Format::of(&path).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_unpack_file() -> Result<()> {
+        let dir = tempfile::tempdir_in(".")?;
+        let path = dir.path().join("a");
+        let limits = Limits {
+            max_size: 4,
+            max_entries: 1,
+        };
+
+        unpack_file(&b"abcd"[..], &path, "a", limits)?;
+        assert_eq!(fs::read(&path)?, b"abcd");
+
+        assert!(unpack_file(&b"abcde"[..], &path, "a", limits).is_err());
+        assert!(!path.exists());
+        Ok(())
+    }
+}
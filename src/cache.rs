@@ -0,0 +1,512 @@
+use crate::config::Config;
+use crate::util;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Time after which a file no entry refers to is no longer
+/// considered to be written by another process.
+const STALE_AFTER: Duration = Duration::from_secs(60 * 60);
+
+/// Cache of downloaded release assets, shared by every root.
+///
+/// Assets are stored by the SHA-256 of their content, so the same
+/// file is only stored once, with an index mapping URLs to hashes.
+/// The least recently used assets are evicted when the cache grows
+/// beyond its maximum size.
+pub struct DownloadCache {
+    dir: PathBuf,
+    max_size: u64,
+}
+
+/// An asset in the cache.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct CacheEntry {
+    pub url: String,
+    pub sha256: String,
+    pub size: u64,
+    /// Unix time (seconds) when the asset was last downloaded or used.
+    pub last_used: u64,
+}
+
+impl CacheEntry {
+    /// Gives the file name in the URL.
+    pub fn name(&self) -> &str {
+        self.url.rsplit('/').next().unwrap_or(&self.url)
+    }
+
+    /// Gives the time since the asset was last used.
+    pub fn age(&self) -> Duration {
+        Duration::from_secs(now().saturating_sub(self.last_used))
+    }
+}
+
+#[derive(Default, Deserialize, Serialize)]
+struct Index {
+    entries: Vec<CacheEntry>,
+}
+
+impl DownloadCache {
+    pub fn new(dir: PathBuf, max_size: u64) -> Self {
+        Self { dir, max_size }
+    }
+
+    /// Gives the cache of the configuration, if it has a cache directory.
+    pub fn from_config(cfg: &Config) -> Option<Self> {
+        let dir = cfg.cache.dir.clone()?;
+        Some(Self::new(dir, cfg.cache.max_size * 1024 * 1024))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn max_size(&self) -> u64 {
+        self.max_size
+    }
+
+    /// Gives the cached asset downloaded from the URL, or with the
+    /// given hash if known, e.g. the same asset from a mirror.
+    pub fn get(&self, url: &str, sha256: Option<&str>) -> Option<CacheEntry> {
+        let _lock = match self.lock() {
+            Ok(lock) => lock,
+            Err(err) => {
+                log::warn!("Failed to lock download cache: {}", err);
+                return None;
+            }
+        };
+
+        let mut index = self.read_index();
+        let sha256 = match sha256 {
+            Some(sha256) => valid_sha256(sha256)?,
+            None => index.entries.iter().find(|e| e.url == url)?.sha256.clone(),
+        };
+
+        let path = self.blob_path(&sha256);
+        let size = fs::metadata(&path).ok()?.len();
+        match util::sha256_file(&path) {
+            Ok(actual) if actual == sha256 => {}
+            _ => {
+                log::warn!("Removing corrupted cache entry {:?}", path);
+                let _ = fs::remove_file(&path);
+                return None;
+            }
+        }
+
+        let entry = upsert(&mut index, url, &sha256, size);
+        if let Err(err) = self.write_index(&index) {
+            log::warn!("Failed to update download cache index: {}", err);
+        }
+
+        Some(entry)
+    }
+
+    /// Gives the path of the cached asset.
+    pub fn path(&self, entry: &CacheEntry) -> PathBuf {
+        self.blob_path(&entry.sha256)
+    }
+
+    /// Checks if the asset downloaded from the URL, or with the given
+    /// hash if known, is cached. This is only a hint, as the content
+    /// isn't verified; `get` removes the asset if it's corrupted.
+    pub fn contains(&self, url: &str, sha256: Option<&str>) -> bool {
+        let _lock = match self.lock() {
+            Ok(lock) => lock,
+            Err(err) => {
+                log::warn!("Failed to lock download cache: {}", err);
+                return false;
+            }
+        };
+
+        let sha256 = match sha256 {
+            Some(sha256) => match valid_sha256(sha256) {
+                Some(sha256) => sha256,
+                None => return false,
+            },
+            None => match self.read_index().entries.into_iter().find(|e| e.url == url) {
+                Some(entry) => entry.sha256,
+                None => return false,
+            },
+        };
+
+        self.blob_path(&sha256).exists()
+    }
+
+    /// Adds a copy of the asset downloaded from the URL to `path`,
+    /// evicting the least recently used assets if the cache grows
+    /// too large.
+    pub fn put(&self, url: &str, path: &Path, sha256: &str) -> Result<()> {
+        let sha256 = &valid_sha256(sha256).ok_or_else(|| anyhow!("invalid SHA-256: {}", sha256))?;
+        let _lock = self.lock()?;
+        let blob = self.blob_path(sha256);
+        if !blob.exists() {
+            let dir = self.dir.join("blobs");
+            fs::create_dir_all(&dir)?;
+
+            // Copied through a temporary file, so that other processes
+            // sharing the cache never see a partial file.
+            let tmp = blob.with_extension(format!("tmp-{}", std::process::id()));
+            fs::copy(path, &tmp)?;
+            fs::rename(&tmp, &blob)?;
+        }
+
+        let size = fs::metadata(&blob)?.len();
+        let mut index = self.read_index();
+        upsert(&mut index, url, sha256, size);
+        self.evict(&mut index)?;
+        self.write_index(&index)
+    }
+
+    /// Gives the cached assets, most recently used first.
+    pub fn entries(&self) -> Vec<CacheEntry> {
+        let mut entries: Vec<CacheEntry> = self
+            .read_index()
+            .entries
+            .into_iter()
+            .filter(|e| self.blob_path(&e.sha256).exists())
+            .collect();
+        entries.sort_by_key(|e| Reverse(e.last_used));
+        entries
+    }
+
+    /// Gives the size of the stored assets.
+    pub fn size(&self) -> u64 {
+        total_size(&self.entries())
+    }
+
+    /// Gives the files in the cache that no entry refers to, e.g. assets
+    /// left behind by a lost index or temporary files of a crashed write.
+    /// Files changed within the last hour are left to writes in progress.
+    pub fn stale_files(&self) -> Result<Vec<PathBuf>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let _lock = self.lock()?;
+        let index = self.read_index();
+        let in_use: HashSet<&str> = index.entries.iter().map(|e| e.sha256.as_str()).collect();
+
+        let mut stale = Vec::new();
+        for dir in [self.dir.clone(), self.dir.join("blobs")] {
+            if !dir.is_dir() {
+                continue;
+            }
+
+            for file in fs::read_dir(&dir)? {
+                let file = file?;
+                let name = file.file_name().to_string_lossy().to_string();
+                let metadata = file.metadata()?;
+                let own = name == "index.json" || name == "index.lock";
+                if !metadata.is_file() || own || in_use.contains(name.as_str()) {
+                    continue;
+                }
+
+                let age = metadata.modified()?.elapsed().unwrap_or_default();
+                if age > STALE_AFTER {
+                    stale.push(file.path());
+                }
+            }
+        }
+
+        Ok(stale)
+    }
+
+    /// Removes every cached asset, giving the reclaimed disk space.
+    /// The lock file is kept, as other processes may be waiting on it.
+    pub fn clear(&self) -> Result<u64> {
+        if !self.dir.exists() {
+            return Ok(0);
+        }
+
+        let _lock = self.lock()?;
+        let mut size = 0;
+        for file in fs::read_dir(&self.dir)? {
+            let path = file?.path();
+            if path.file_name().is_some_and(|name| name == "index.lock") {
+                continue;
+            }
+            size += util::disk_usage(&path)?;
+            if fs::symlink_metadata(&path)?.is_dir() {
+                fs::remove_dir_all(&path)?;
+            } else {
+                fs::remove_file(&path)?;
+            }
+        }
+        Ok(size)
+    }
+
+    fn evict(&self, index: &mut Index) -> Result<()> {
+        index.entries.sort_by_key(|e| Reverse(e.last_used));
+
+        while total_size(&index.entries) > self.max_size && index.entries.len() > 1 {
+            let evicted = index.entries.pop().unwrap();
+            log::info!("Evicting {} from the download cache", evicted.url);
+
+            // Assets are shared by URLs with the same content.
+            if index.entries.iter().all(|e| e.sha256 != evicted.sha256) {
+                let path = self.blob_path(&evicted.sha256);
+                if path.exists() {
+                    fs::remove_file(path)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Locks the cache until the returned file is dropped, so that other
+    /// processes sharing the cache don't lose updates of the index.
+    fn lock(&self) -> Result<fs::File> {
+        fs::create_dir_all(&self.dir)?;
+        let file = fs::File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.dir.join("index.lock"))?;
+        file.lock()?;
+        Ok(file)
+    }
+
+    fn read_index(&self) -> Index {
+        let path = self.index_path();
+        if !path.exists() {
+            return Index::default();
+        }
+
+        match util::json_from_file::<Index>(&path) {
+            // Entries are only used with a valid hash, which is a file name.
+            Ok(mut index) => {
+                index.entries.retain_mut(|e| match valid_sha256(&e.sha256) {
+                    Some(sha256) => {
+                        e.sha256 = sha256;
+                        true
+                    }
+                    None => false,
+                });
+                index
+            }
+            Err(err) => {
+                log::warn!("Ignoring unreadable download cache index: {}", err);
+                Index::default()
+            }
+        }
+    }
+
+    fn write_index(&self, index: &Index) -> Result<()> {
+        write_atomic(&self.index_path(), &serde_json::to_vec(index)?)
+    }
+
+    fn index_path(&self) -> PathBuf {
+        self.dir.join("index.json")
+    }
+
+    fn blob_path(&self, sha256: &str) -> PathBuf {
+        self.dir.join("blobs").join(sha256)
+    }
+}
+
+/// Gives the hash in lower case if it's a SHA-256 in hex, which is
+/// checked before it's used as the file name of a blob, since hashes
+/// may come from elsewhere, e.g. a lockfile.
+fn valid_sha256(sha256: &str) -> Option<String> {
+    let valid = sha256.len() == 64 && sha256.chars().all(|c| c.is_ascii_hexdigit());
+    valid.then(|| sha256.to_ascii_lowercase())
+}
+
+fn upsert(index: &mut Index, url: &str, sha256: &str, size: u64) -> CacheEntry {
+    let entry = CacheEntry {
+        url: url.to_string(),
+        sha256: sha256.to_string(),
+        size,
+        last_used: now(),
+    };
+
+    index.entries.retain(|e| e.url != url);
+    index.entries.push(entry.clone());
+    entry
+}
+
+/// Gives the size of the distinct assets of the entries.
+fn total_size(entries: &[CacheEntry]) -> u64 {
+    let mut seen = HashSet::new();
+    entries
+        .iter()
+        .filter(|e| seen.insert(&e.sha256))
+        .map(|e| e.size)
+        .sum()
+}
+
+/// Writes the file through a temporary file, so that other
+/// processes sharing the cache never see a partial file.
+fn write_atomic(path: &Path, bytes: &[u8]) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let tmp = path.with_extension(format!("tmp-{}", std::process::id()));
+    util::write_file(&tmp, bytes)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const URL: &str = "https://github.com/a/b/releases/download/v1.0.0/b.tar.gz";
+    const MIRROR: &str = "https://mirror.example.com/a/b/v1.0.0/b.tar.gz";
+
+    fn put(cache: &DownloadCache, dir: &Path, url: &str, bytes: &[u8]) -> Result<()> {
+        let path = dir.join("download");
+        fs::write(&path, bytes)?;
+        cache.put(url, &path, &util::sha256_hex(bytes))
+    }
+
+    #[test]
+    fn test_get_put() -> Result<()> {
+        let dir = tempfile::tempdir_in(".")?;
+        let cache = DownloadCache::new(dir.path().join("downloads"), 1024);
+        assert!(!cache.contains(URL, None));
+        assert!(cache.get(URL, None).is_none());
+
+        put(&cache, dir.path(), URL, b"asset")?;
+        assert!(cache.contains(URL, None));
+        let entry = cache.get(URL, None).expect("cached asset");
+        assert_eq!(fs::read(cache.path(&entry))?, b"asset");
+
+        // The same content is found by hash from another URL.
+        let sha256 = util::sha256_hex(b"asset");
+        assert!(!cache.contains(MIRROR, None));
+        assert!(cache.contains(MIRROR, Some(&sha256)));
+        assert!(cache.get(MIRROR, Some(&sha256)).is_some());
+        assert_eq!(cache.entries().len(), 2);
+        assert_eq!(cache.size(), 5);
+        Ok(())
+    }
+
+    #[test]
+    fn test_corrupted() -> Result<()> {
+        let dir = tempfile::tempdir_in(".")?;
+        let cache = DownloadCache::new(dir.path().join("downloads"), 1024);
+        put(&cache, dir.path(), URL, b"asset")?;
+
+        fs::write(cache.blob_path(&util::sha256_hex(b"asset")), b"other")?;
+        assert!(cache.get(URL, None).is_none());
+        assert!(!cache.contains(URL, None));
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_sha256() -> Result<()> {
+        let dir = tempfile::tempdir_in(".")?;
+        let cache = DownloadCache::new(dir.path().join("downloads"), 1024);
+        put(&cache, dir.path(), URL, b"asset")?;
+
+        // Hashes from e.g. a lockfile are never used as paths unchecked.
+        let outside = dir.path().join("outside");
+        fs::write(&outside, b"keep")?;
+        let traversal = "../../outside";
+        assert!(cache.get(MIRROR, Some(traversal)).is_none());
+        assert!(!cache.contains(MIRROR, Some(traversal)));
+        assert!(outside.exists());
+
+        // Upper case hex is the same hash, and keeps the blob.
+        let sha256 = util::sha256_hex(b"asset").to_uppercase();
+        assert!(cache.contains(MIRROR, Some(&sha256)));
+        assert!(cache.get(MIRROR, Some(&sha256)).is_some());
+        assert!(cache.get(URL, None).is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_clear() -> Result<()> {
+        let dir = tempfile::tempdir_in(".")?;
+        let cache = DownloadCache::new(dir.path().join("downloads"), 1024);
+        put(&cache, dir.path(), URL, b"asset")?;
+
+        assert!(cache.clear()? > 0);
+        assert!(cache.entries().is_empty());
+        assert!(!cache.contains(URL, None));
+        Ok(())
+    }
+
+    #[test]
+    fn test_concurrent_puts() -> Result<()> {
+        let dir = tempfile::tempdir_in(".")?;
+        let cache = DownloadCache::new(dir.path().join("downloads"), 1024 * 1024);
+
+        std::thread::scope(|scope| {
+            for i in 0..8 {
+                let (cache, dir) = (&cache, dir.path().join(i.to_string()));
+                scope.spawn(move || -> Result<()> {
+                    fs::create_dir(&dir)?;
+                    put(
+                        cache,
+                        &dir,
+                        &format!("{}/{}", URL, i),
+                        i.to_string().as_bytes(),
+                    )
+                });
+            }
+        });
+
+        assert_eq!(cache.entries().len(), 8);
+        Ok(())
+    }
+
+    #[test]
+    fn test_stale_files() -> Result<()> {
+        let dir = tempfile::tempdir_in(".")?;
+        let cache = DownloadCache::new(dir.path().join("downloads"), 1024);
+        put(&cache, dir.path(), URL, b"asset")?;
+
+        let orphan = cache.blob_path(&util::sha256_hex(b"orphan"));
+        let tmp = cache.index_path().with_extension("tmp-1");
+        fs::write(&orphan, b"orphan")?;
+        fs::write(&tmp, b"{}")?;
+        assert!(cache.stale_files()?.is_empty());
+
+        let old = SystemTime::now() - 2 * STALE_AFTER;
+        for path in [&orphan, &tmp] {
+            fs::File::options()
+                .write(true)
+                .open(path)?
+                .set_modified(old)?;
+        }
+        let mut stale = cache.stale_files()?;
+        stale.sort();
+        let mut expected = vec![tmp, orphan];
+        expected.sort();
+        assert_eq!(stale, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_evict() -> Result<()> {
+        let dir = tempfile::tempdir_in(".")?;
+        let cache = DownloadCache::new(dir.path().join("downloads"), 10);
+
+        put(&cache, dir.path(), "old", b"old-asset")?;
+        let mut index = cache.read_index();
+        index.entries[0].last_used -= 60;
+        cache.write_index(&index)?;
+
+        put(&cache, dir.path(), URL, b"new-asset")?;
+        let entries = cache.entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].url, URL);
+        assert!(!cache.blob_path(&util::sha256_hex(b"old-asset")).exists());
+        Ok(())
+    }
+}
@@ -1,4 +1,5 @@
 use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
 
 /// dti -- manage your code tools with ease.
 #[derive(Parser)]
@@ -8,6 +9,9 @@ pub struct Cli {
     pub command: Command,
     #[arg(short, long, action = clap::ArgAction::Count, global = true)]
     pub verbose: u8,
+    /// Only use cached release data and downloads, never the network.
+    #[arg(long, global = true)]
+    pub offline: bool,
 }
 
 #[derive(Subcommand)]
@@ -22,12 +26,36 @@ pub enum Command {
     /// Install a package.
     #[command(visible_alias = "i")]
     Install(InstallArgs),
+    /// Write a lockfile for the packages in ensure-installed.
+    Lock(LockArgs),
+    /// Export the installed packages to a file.
+    Export(ExportArgs),
+    /// Install the packages in a file written by export.
+    Import(ImportArgs),
+    /// Check that installed packages are intact on disk.
+    Verify(VerifyArgs),
+    /// Add packages found on disk to the manifest.
+    Adopt,
+    /// Remove leftover downloads and orphaned package directories.
+    ///
+    /// Removes downloaded archives and partial downloads in package
+    /// directories, directories of packages that are no longer installed
+    /// and files in the download cache that no cached asset refers to.
+    /// Old versions of packages aren't retained, as a package is uninstalled
+    /// before another version is installed; assets of old releases in the
+    /// download cache may be used by other roots, and are evicted when the
+    /// cache grows too large or removed with "cache clear".
+    Clean(CleanArgs),
+    /// Manage the download cache shared by every root.
+    Cache(CacheArgs),
     /// Uninstall a package.
     #[command(visible_alias = "rm")]
     Remove { name: String },
     /// Updates a package.
     #[command(visible_alias = "up")]
     Update(UpdateArgs),
+    /// List the releases of a package, newest first.
+    Versions(VersionsArgs),
 }
 
 #[derive(Args)]
@@ -56,6 +84,54 @@ pub struct InstallArgs {
     /// Latest version is resolved by default.
     #[arg(long)]
     pub version: Option<String>,
+    /// Install exactly the packages in the lockfile.
+    #[arg(long, conflicts_with_all = ["name", "version"])]
+    pub locked: bool,
+    /// Path to the lockfile used with --locked.
+    #[arg(long, default_value = "dti.lock")]
+    pub lockfile: PathBuf,
+    /// Install release assets without verifying their signatures,
+    /// even for packages configured to require them.
+    #[arg(long)]
+    pub skip_signatures: bool,
+}
+
+#[derive(Args)]
+pub struct LockArgs {
+    /// Path to the lockfile to write.
+    #[arg(long, default_value = "dti.lock")]
+    pub lockfile: PathBuf,
+}
+
+#[derive(Args)]
+pub struct ExportArgs {
+    /// Path of the file to write.
+    #[arg(default_value = "dti-packages.toml")]
+    pub file: PathBuf,
+}
+
+#[derive(Args)]
+pub struct ImportArgs {
+    /// Path of a file written by export.
+    #[arg()]
+    pub file: PathBuf,
+    /// Only report what would be installed or updated.
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Args)]
+pub struct VerifyArgs {
+    /// Reinstall broken packages and drop stale entries.
+    #[arg(long)]
+    pub fix: bool,
+}
+
+#[derive(Args)]
+pub struct CleanArgs {
+    /// Only report what would be removed.
+    #[arg(long)]
+    pub dry_run: bool,
 }
 
 #[derive(Args)]
@@ -68,4 +144,30 @@ pub struct UpdateArgs {
     /// Latest version is resolved by default.
     #[arg(long)]
     pub version: Option<String>,
+    /// Install release assets without verifying their signatures,
+    /// even for packages configured to require them.
+    #[arg(long)]
+    pub skip_signatures: bool,
+}
+
+#[derive(Args)]
+pub struct VersionsArgs {
+    /// Name of the package.
+    #[arg()]
+    pub name: String,
+}
+
+#[derive(Args)]
+pub struct CacheArgs {
+    #[command(subcommand)]
+    pub command: CacheCommand,
+}
+
+#[derive(Subcommand)]
+pub enum CacheCommand {
+    /// List the cached downloads, most recently used first.
+    #[command(visible_alias = "ls")]
+    List,
+    /// Remove every cached download.
+    Clear,
 }
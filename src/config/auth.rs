@@ -0,0 +1,403 @@
+use super::HostKind;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::{fmt, fs, process};
+
+/// Authentication in requests to the GitHub API,
+/// as given in the configuration file.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Auth {
+    /// A personal access token.
+    pub token: Option<String>,
+    /// A command that prints a token on stdout, e.g. `pass show github`.
+    #[serde(rename = "token-command")]
+    pub token_command: Option<String>,
+    /// Optional GitHub OAuth application.
+    #[serde(rename = "client-id")]
+    pub client_id: Option<String>,
+    #[serde(rename = "client-secret")]
+    pub client_secret: Option<String>,
+}
+
+/// Where the credentials in use were found.
+#[derive(Clone)]
+pub enum Source {
+    Env(&'static str),
+    ConfigToken,
+    ConfigCommand,
+    ConfigOAuthApp,
+    GhCli(PathBuf),
+    Netrc(PathBuf),
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Env(var) => write!(f, "{} environment variable", var),
+            Source::ConfigToken => write!(f, "token in configuration"),
+            Source::ConfigCommand => write!(f, "token-command in configuration"),
+            Source::ConfigOAuthApp => write!(f, "OAuth app in configuration"),
+            Source::GhCli(path) => write!(f, "gh CLI ({})", path.display()),
+            Source::Netrc(path) => write!(f, "netrc ({})", path.display()),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub enum Credentials {
+    /// Sent as a bearer token.
+    Token { token: String, source: Source },
+    /// Sent using basic authentication.
+    OAuthApp {
+        client_id: String,
+        client_secret: String,
+    },
+}
+
+impl Credentials {
+    pub fn source(&self) -> Source {
+        match self {
+            Credentials::Token { source, .. } => source.clone(),
+            Credentials::OAuthApp { .. } => Source::ConfigOAuthApp,
+        }
+    }
+
+    /// Resolves the credentials to use for `host`, trying in order:
+    ///
+    /// 1. the GITHUB_TOKEN and GH_TOKEN environment variables
+    ///    (GITHUB_ENTERPRISE_TOKEN and GH_ENTERPRISE_TOKEN for other hosts)
+    /// 2. token in the configuration
+    /// 3. token-command in the configuration
+    /// 4. client-id and client-secret in the configuration
+    /// 5. the hosts.yml of the gh CLI
+    /// 6. ~/.netrc
+    ///
+    /// For GitLab hosts the GITLAB_TOKEN environment variable is used
+    /// instead, and for Gitea hosts GITEA_TOKEN and FORGEJO_TOKEN.
+    pub fn resolve(auth: Option<&Auth>, host: &str, kind: HostKind) -> Option<Self> {
+        let home = directories::BaseDirs::new().map(|dirs| dirs.home_dir().to_path_buf());
+        resolve(
+            auth,
+            host,
+            kind,
+            |var| std::env::var(var).ok(),
+            home.as_deref(),
+        )
+    }
+}
+
+fn resolve<E>(
+    auth: Option<&Auth>,
+    host: &str,
+    kind: HostKind,
+    env: E,
+    home: Option<&Path>,
+) -> Option<Credentials>
+where
+    E: Fn(&str) -> Option<String>,
+{
+    let token = |token: String, source: Source| {
+        log::info!("Using credentials for {} from {}", host, source);
+        Some(Credentials::Token { token, source })
+    };
+
+    // Like the gh CLI, other GitHub hosts than github.com
+    // are assumed to be GitHub Enterprise instances.
+    let vars: &[&'static str] = match kind {
+        HostKind::GitLab => &["GITLAB_TOKEN"],
+        HostKind::Gitea => &["GITEA_TOKEN", "FORGEJO_TOKEN"],
+        HostKind::GitHub if host == "github.com" => &["GITHUB_TOKEN", "GH_TOKEN"],
+        HostKind::GitHub => &["GITHUB_ENTERPRISE_TOKEN", "GH_ENTERPRISE_TOKEN"],
+    };
+
+    for &var in vars {
+        if let Some(t) = env(var).filter(|t| !t.is_empty()) {
+            return token(t, Source::Env(var));
+        }
+    }
+
+    if let Some(auth) = auth {
+        if let Some(t) = &auth.token {
+            return token(t.clone(), Source::ConfigToken);
+        }
+
+        if let Some(command) = &auth.token_command {
+            match run_token_command(command) {
+                Some(t) => return token(t, Source::ConfigCommand),
+                None => log::warn!("token-command failed: {}", command),
+            }
+        }
+
+        if let (Some(client_id), Some(client_secret)) = (&auth.client_id, &auth.client_secret) {
+            log::info!("Using GitHub OAuth app from configuration");
+            return Some(Credentials::OAuthApp {
+                client_id: client_id.clone(),
+                client_secret: client_secret.clone(),
+            });
+        }
+    }
+
+    let gh_config = match env("GH_CONFIG_DIR") {
+        Some(dir) => Some(PathBuf::from(dir)),
+        None => match env("XDG_CONFIG_HOME") {
+            Some(dir) => Some(PathBuf::from(dir).join("gh")),
+            None => home.map(|home| home.join(".config").join("gh")),
+        },
+    };
+    if let Some(path) = gh_config.map(|dir| dir.join("hosts.yml")) {
+        if let Some(t) = read_file(&path).and_then(|s| parse_gh_hosts(&s, host)) {
+            return token(t, Source::GhCli(path));
+        }
+    }
+
+    let netrc = match env("NETRC") {
+        Some(path) => Some(PathBuf::from(path)),
+        None => home.map(|home| home.join(".netrc")),
+    };
+    if let Some(path) = netrc {
+        if let Some(content) = read_file(&path) {
+            // Tokens for github.com are usually given for the API host.
+            let api_host = format!("api.{}", host);
+            let t = parse_netrc(&content, &api_host).or_else(|| parse_netrc(&content, host));
+            if let Some(t) = t {
+                return token(t, Source::Netrc(path));
+            }
+        }
+    }
+
+    None
+}
+
+fn read_file(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok()
+}
+
+fn run_token_command(command: &str) -> Option<String> {
+    let output = process::Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stderr(process::Stdio::inherit())
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    let token = String::from_utf8(output.stdout).ok()?;
+    let token = token.trim();
+    if token.is_empty() {
+        None
+    } else {
+        Some(token.to_string())
+    }
+}
+
+/// Finds the oauth_token of `host` in the hosts.yml of the gh CLI. With
+/// several accounts, the token is either given for the host or under the
+/// active `user` in `users`:
+///
+/// ```yaml
+/// github.com:
+///     users:
+///         monalisa:
+///             oauth_token: gho_yyy
+///         octocat:
+///             oauth_token: gho_xxx
+///     user: octocat
+/// ```
+fn parse_gh_hosts(content: &str, host: &str) -> Option<String> {
+    let mut in_host = false;
+    // Indentation of the keys of the host, and of the users in `users`.
+    let mut host_indent = None;
+    let mut user_indent = None;
+    let mut in_users = false;
+    let mut current_user: Option<&str> = None;
+
+    let mut host_token = None;
+    let mut active_user = None;
+    let mut user_tokens: HashMap<&str, &str> = HashMap::new();
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        let indent = line.len() - line.trim_start().len();
+        if indent == 0 {
+            in_host = trimmed.trim_end_matches(':') == host;
+            host_indent = None;
+            in_users = false;
+            continue;
+        }
+        if !in_host {
+            continue;
+        }
+
+        let (key, value) = trimmed.split_once(':').unwrap_or((trimmed, ""));
+        let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+        let value = (!value.is_empty()).then_some(value);
+
+        if indent == *host_indent.get_or_insert(indent) {
+            in_users = key == "users";
+            user_indent = None;
+            current_user = None;
+            match key {
+                "oauth_token" => host_token = value,
+                "user" => active_user = value,
+                _ => {}
+            }
+        } else if in_users {
+            if indent == *user_indent.get_or_insert(indent) {
+                current_user = Some(key);
+            } else if let (Some(user), "oauth_token", Some(token)) = (current_user, key, value) {
+                user_tokens.insert(user, token);
+            }
+        }
+    }
+
+    host_token
+        .or_else(|| user_tokens.get(active_user?).copied())
+        .map(|token| token.to_string())
+}
+
+/// Finds the password of `machine` in a netrc file.
+fn parse_netrc(content: &str, machine: &str) -> Option<String> {
+    let mut tokens = content.split_whitespace();
+    let mut current: Option<&str> = None;
+
+    while let Some(token) = tokens.next() {
+        match token {
+            "machine" => current = tokens.next(),
+            "default" => current = None,
+            "password" => {
+                let password = tokens.next();
+                if current == Some(machine) {
+                    return password.map(|p| p.to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn token_of(credentials: Option<Credentials>) -> Option<String> {
+        match credentials {
+            Some(Credentials::Token { token, .. }) => Some(token),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_parse_gh_hosts() {
+        let content = "\
+github.example.com:
+    oauth_token: other
+github.com:
+    users:
+        monalisa:
+            oauth_token: gho_other
+        octocat:
+            oauth_token: gho_abc
+    git_protocol: https
+    user: octocat
+gitlab.com:
+    user: octocat
+";
+        assert_eq!(
+            parse_gh_hosts(content, "github.com").as_deref(),
+            Some("gho_abc")
+        );
+
+        // A token of the host itself is preferred.
+        let single = "github.com:\n    oauth_token: gho_host\n    user: octocat\n";
+        assert_eq!(
+            parse_gh_hosts(single, "github.com").as_deref(),
+            Some("gho_host")
+        );
+        assert_eq!(
+            parse_gh_hosts(content, "github.example.com").as_deref(),
+            Some("other")
+        );
+        assert!(parse_gh_hosts(content, "gitlab.com").is_none());
+    }
+
+    #[test]
+    fn test_parse_netrc() {
+        let content = "machine example.com login a password b\n\
+                       machine api.github.com\n  login octocat\n  password ghp_abc\n";
+        assert_eq!(
+            parse_netrc(content, "api.github.com").as_deref(),
+            Some("ghp_abc")
+        );
+        assert!(parse_netrc(content, "github.com").is_none());
+    }
+
+    fn lookup(env: &HashMap<&'static str, &'static str>) -> impl Fn(&str) -> Option<String> {
+        let env = env.clone();
+        move |var| env.get(var).map(|v| v.to_string())
+    }
+
+    #[test]
+    fn test_precedence() -> anyhow::Result<()> {
+        let home = tempfile::tempdir_in(".")?;
+        fs::write(
+            home.path().join(".netrc"),
+            "machine api.github.com password from-netrc",
+        )?;
+
+        let mut env: HashMap<&'static str, &'static str> = HashMap::new();
+
+        let got = resolve(
+            None,
+            "github.com",
+            HostKind::GitHub,
+            lookup(&env),
+            Some(home.path()),
+        );
+        assert_eq!(token_of(got).as_deref(), Some("from-netrc"));
+
+        let auth = Auth {
+            token: Some("from-config".into()),
+            ..Default::default()
+        };
+        let got = resolve(
+            Some(&auth),
+            "github.com",
+            HostKind::GitHub,
+            lookup(&env),
+            Some(home.path()),
+        );
+        assert_eq!(token_of(got).as_deref(), Some("from-config"));
+
+        env.insert("GH_TOKEN", "from-gh-token");
+        let got = resolve(
+            Some(&auth),
+            "github.com",
+            HostKind::GitHub,
+            lookup(&env),
+            Some(home.path()),
+        );
+        assert_eq!(token_of(got).as_deref(), Some("from-gh-token"));
+
+        env.insert("GITHUB_TOKEN", "from-github-token");
+        let got = resolve(
+            Some(&auth),
+            "github.com",
+            HostKind::GitHub,
+            lookup(&env),
+            Some(home.path()),
+        );
+        assert_eq!(token_of(got).as_deref(), Some("from-github-token"));
+        Ok(())
+    }
+}
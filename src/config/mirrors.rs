@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+
+/// The npm registry used unless rewritten to a mirror.
+pub const NPM_REGISTRY: &str = "https://registry.npmjs.org/";
+/// The PyPI index used unless rewritten to a mirror.
+pub const PYPI_INDEX: &str = "https://pypi.org/simple/";
+/// The Go module proxy used unless rewritten to a mirror.
+pub const GOPROXY: &str = "https://proxy.golang.org/";
+/// The sparse index of crates.io used unless rewritten to a mirror.
+pub const CRATES_INDEX: &str = "https://index.crates.io/";
+
+/// URL rewrite rules, each mapping a URL prefix to the prefix of a
+/// mirror, e.g. a remote repository of Artifactory or Nexus.
+#[derive(Clone, Default)]
+pub struct Mirrors {
+    /// Rules sorted by longest prefix first, so the most specific applies.
+    rules: Vec<(String, String)>,
+}
+
+impl Mirrors {
+    pub fn new(rules: HashMap<String, String>) -> Self {
+        let mut rules: Vec<(String, String)> = rules.into_iter().collect();
+        rules.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then_with(|| a.0.cmp(&b.0)));
+        Self { rules }
+    }
+
+    /// Gives the URL of the mirror, if a rule matches the URL.
+    pub fn mirror(&self, url: &str) -> Option<String> {
+        self.rules.iter().find_map(|(prefix, mirror)| {
+            url.strip_prefix(prefix.as_str())
+                .map(|rest| format!("{}{}", mirror, rest))
+        })
+    }
+
+    /// Gives the URL to use for the URL: the one of the mirror
+    /// if a rule matches, otherwise the URL itself.
+    pub fn rewrite(&self, url: &str) -> String {
+        self.mirror(url).unwrap_or_else(|| url.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewrite() {
+        let mirrors = Mirrors::new(HashMap::from([
+            (
+                "https://github.com/".to_string(),
+                "https://repo.example.com/github/".to_string(),
+            ),
+            (
+                "https://github.com/acme/".to_string(),
+                "https://repo.example.com/acme/".to_string(),
+            ),
+            (
+                NPM_REGISTRY.to_string(),
+                "https://repo.example.com/api/npm/npm/".to_string(),
+            ),
+        ]));
+
+        assert_eq!(
+            mirrors.rewrite("https://github.com/a/b/releases/download/v1/b.tar.gz"),
+            "https://repo.example.com/github/a/b/releases/download/v1/b.tar.gz"
+        );
+        assert_eq!(
+            mirrors.rewrite("https://github.com/acme/tool"),
+            "https://repo.example.com/acme/tool"
+        );
+        assert_eq!(
+            mirrors.mirror(NPM_REGISTRY).as_deref(),
+            Some("https://repo.example.com/api/npm/npm/")
+        );
+        assert_eq!(
+            mirrors.rewrite("https://api.github.com/repos/a/b"),
+            "https://api.github.com/repos/a/b"
+        );
+        assert!(mirrors.mirror(PYPI_INDEX).is_none());
+    }
+}
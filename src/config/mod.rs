@@ -1,31 +1,263 @@
-use crate::{pkg::Version, util};
-use anyhow::Result;
+use crate::pkg::{Signing, Version};
+use crate::url_source::UrlSource;
+use crate::util;
+use anyhow::{bail, Context, Result};
+use reqwest::{Certificate, Proxy};
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, path::Path};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
-/// Optional GitHub OAuth application
-/// that can be used for authentication
-/// in requests to GitHub API.
-#[derive(Debug, Deserialize, Serialize)]
-pub struct Auth {
-    #[serde(rename = "client-id")]
-    pub client_id: String,
-    #[serde(rename = "client-secret")]
-    pub client_secret: String,
-}
+mod auth;
+mod mirrors;
+
+pub use auth::{Auth, Credentials};
+pub use mirrors::{Mirrors, CRATES_INDEX, GOPROXY, NPM_REGISTRY, PYPI_INDEX};
 
 #[derive(Default, Deserialize, Serialize)]
 pub struct PackageConfig {
     pub version: Option<Version>,
+    /// Install the package from this repository instead,
+    /// e.g. a mirror on a GitHub Enterprise instance.
+    pub repo: Option<String>,
+    /// Refuse to install release assets without a published checksum.
+    #[serde(rename = "require-checksum", default)]
+    pub require_checksum: bool,
+    /// Verify the signatures of release assets, e.g.
+    /// `signature = { scheme = "minisign", public-key = "..." }`.
+    pub signature: Option<Signing>,
+    /// Name of the release asset for this system, e.g.
+    /// `lazygit_{version}_Linux_x86_64.tar.gz`, used when
+    /// resolving releases without the API.
+    #[serde(rename = "asset-template")]
+    pub asset_template: Option<String>,
+    /// Resolve releases from a version index and download URL
+    /// template, e.g. of a vendor's download site.
+    #[serde(rename = "url-source")]
+    pub url_source: Option<UrlSource>,
+    /// Resolve releases from the Gitea or Forgejo instance
+    /// with this API URL, e.g. `https://git.example.com/api/v1`.
+    pub gitea: Option<String>,
+}
+
+/// Configuration of a GitHub Enterprise or GitLab host.
+#[derive(Default, Deserialize, Serialize)]
+pub struct HostConfig {
+    /// The kind of host, GitHub unless given.
+    #[serde(default)]
+    pub kind: HostKind,
+    /// Base URL of the API, defaults to https://<host>/api/v3 for
+    /// GitHub, https://<host>/api/v4 for GitLab and
+    /// https://<host>/api/v1 for Gitea.
+    #[serde(rename = "api-url")]
+    pub api_url: Option<String>,
+    pub auth: Option<Auth>,
+}
+
+/// The kind of a host, which decides how releases are resolved.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HostKind {
+    #[default]
+    GitHub,
+    GitLab,
+    /// Gitea, or Forgejo which has the same API.
+    #[serde(alias = "forgejo")]
+    Gitea,
+}
+
+/// A host with its API URL and credentials.
+#[derive(Clone)]
+pub struct Host {
+    pub kind: HostKind,
+    pub api_url: String,
+    pub credentials: Option<Credentials>,
+}
+
+/// The host of github.com.
+pub const GITHUB: &str = "github.com";
+
+/// The host of gitlab.com.
+pub const GITLAB: &str = "gitlab.com";
+
+/// The host of Codeberg, which runs Forgejo.
+pub const CODEBERG: &str = "codeberg.org";
+
+/// Gives the host of github.com, whose API isn't at /api/v3.
+fn github_host(credentials: Option<Credentials>) -> Host {
+    Host {
+        kind: HostKind::GitHub,
+        api_url: "https://api.github.com".to_string(),
+        credentials,
+    }
 }
 
 type PackageConfigs = HashMap<String, PackageConfig>;
 
-#[derive(Default)]
+/// Settings for requests to the GitHub API.
+#[derive(Deserialize, Serialize)]
+pub struct GitHubConfig {
+    /// Seconds to use a cached API response without asking
+    /// GitHub if it has changed.
+    #[serde(rename = "cache-ttl", default = "default_cache_ttl")]
+    pub cache_ttl: u64,
+    /// Seconds to wait for the rate limit to reset, instead of
+    /// failing, when it's exceeded. Disabled by default.
+    #[serde(rename = "rate-limit-wait", default)]
+    pub rate_limit_wait: u64,
+    /// How to resolve releases. The web pages of repos are
+    /// used anyway when the API is rate limited.
+    #[serde(default)]
+    pub resolve: Resolve,
+}
+
+impl Default for GitHubConfig {
+    fn default() -> Self {
+        Self {
+            cache_ttl: default_cache_ttl(),
+            rate_limit_wait: 0,
+            resolve: Resolve::default(),
+        }
+    }
+}
+
+/// How releases are resolved.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Resolve {
+    /// Using the REST API, which lists the release assets.
+    #[default]
+    Api,
+    /// Following the releases/latest redirect of the web pages of repos,
+    /// which isn't rate limited. Assets are named by asset templates.
+    Redirect,
+}
+
+fn default_cache_ttl() -> u64 {
+    300
+}
+
+/// Settings for all network requests.
+#[derive(Deserialize, Serialize)]
+pub struct NetworkConfig {
+    /// Seconds to wait for a connection to be established.
+    #[serde(rename = "connect-timeout", default = "default_connect_timeout")]
+    pub connect_timeout: u64,
+    /// Seconds to wait for a response, or for more
+    /// data when reading a response.
+    #[serde(rename = "read-timeout", default = "default_read_timeout")]
+    pub read_timeout: u64,
+    /// Number of times to retry requests that fail
+    /// because of e.g. server or connection errors.
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+    /// Proxy to use for all requests, instead of the
+    /// one given by e.g. the HTTPS_PROXY environment variable.
+    pub proxy: Option<String>,
+    /// Comma separated hosts to not use the proxy for.
+    #[serde(rename = "no-proxy")]
+    pub no_proxy: Option<String>,
+    /// PEM files with certificates to trust in addition
+    /// to the ones of the system.
+    #[serde(rename = "ca-certificates", default)]
+    pub ca_certificates: Vec<PathBuf>,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout: default_connect_timeout(),
+            read_timeout: default_read_timeout(),
+            retries: default_retries(),
+            proxy: None,
+            no_proxy: None,
+            ca_certificates: Vec::new(),
+        }
+    }
+}
+
+/// Settings of the download cache shared by every root.
+#[derive(Deserialize, Serialize)]
+pub struct CacheConfig {
+    /// Defaults to the user cache directory, e.g. ~/.cache/devtoolinstaller.
+    pub dir: Option<PathBuf>,
+    /// Maximum size in MiB before the least recently used
+    /// downloads are evicted.
+    #[serde(rename = "max-size", default = "default_max_size")]
+    pub max_size: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            dir: None,
+            max_size: default_max_size(),
+        }
+    }
+}
+
+fn default_max_size() -> u64 {
+    2048
+}
+
+fn default_connect_timeout() -> u64 {
+    10
+}
+
+fn default_read_timeout() -> u64 {
+    30
+}
+
+fn default_retries() -> u32 {
+    3
+}
+
 pub struct Config {
     pub ensure_installed: Option<Vec<String>>,
     pub package_configs: PackageConfigs,
     pub auth: Option<Auth>,
+    /// Credentials for the GitHub API, resolved from
+    /// the environment and the auth configuration.
+    pub credentials: Option<Credentials>,
+    /// GitHub hosts by name, including github.com.
+    pub hosts: HashMap<String, Host>,
+    pub github: GitHubConfig,
+    pub network: NetworkConfig,
+    /// URL rewrite rules for the GitHub API, downloads
+    /// and the registries of package managers.
+    pub mirrors: Mirrors,
+    /// Proxy given by the network configuration.
+    pub proxy: Option<Proxy>,
+    /// Additional trusted certificates given by the network configuration.
+    pub certificates: Vec<Certificate>,
+    /// Directory for cached data, next to the configuration file.
+    /// Nothing is cached if not set.
+    pub cache_dir: Option<PathBuf>,
+    /// The shared download cache, nothing is
+    /// downloaded to it if it has no directory.
+    pub cache: CacheConfig,
+    /// Work from cached release data and downloads only,
+    /// given by the --offline flag rather than the file.
+    pub offline: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            ensure_installed: None,
+            package_configs: PackageConfigs::new(),
+            auth: None,
+            credentials: None,
+            hosts: HashMap::from([(GITHUB.to_string(), github_host(None))]),
+            github: GitHubConfig::default(),
+            network: NetworkConfig::default(),
+            proxy: None,
+            certificates: Vec::new(),
+            cache_dir: None,
+            cache: CacheConfig::default(),
+            offline: false,
+            mirrors: Mirrors::default(),
+        }
+    }
 }
 
 impl Config {
@@ -42,19 +274,92 @@ impl Config {
 
         if let Some(pkgs) = file_config.packages {
             ensure_installed = pkgs.ensure_installed;
-            package_configs = match pkgs.config {
-                Some(cfg) => cfg,
-                None => PackageConfigs::new(),
-            };
+            package_configs = pkgs.config.unwrap_or_default();
         } else {
             ensure_installed = None;
             package_configs = PackageConfigs::new();
         }
 
+        let credentials = Credentials::resolve(file_config.auth.as_ref(), GITHUB, HostKind::GitHub);
+
+        let mut hosts = HashMap::new();
+        for (name, host) in file_config.hosts.unwrap_or_default() {
+            let api_url = match (host.api_url, host.kind) {
+                (Some(url), _) => url.trim_end_matches('/').to_string(),
+                (None, HostKind::GitHub) => format!("https://{}/api/v3", name),
+                (None, HostKind::GitLab) => format!("https://{}/api/v4", name),
+                (None, HostKind::Gitea) => format!("https://{}/api/v1", name),
+            };
+            let credentials = Credentials::resolve(host.auth.as_ref(), &name, host.kind);
+            hosts.insert(
+                name,
+                Host {
+                    kind: host.kind,
+                    api_url,
+                    credentials,
+                },
+            );
+        }
+        hosts.insert(GITHUB.to_string(), github_host(credentials.clone()));
+        for (name, kind, api_url) in [
+            (GITLAB, HostKind::GitLab, "https://gitlab.com/api/v4"),
+            (CODEBERG, HostKind::Gitea, "https://codeberg.org/api/v1"),
+        ] {
+            if !hosts.contains_key(name) {
+                let host = Host {
+                    kind,
+                    api_url: api_url.to_string(),
+                    credentials: Credentials::resolve(None, name, kind),
+                };
+                hosts.insert(name.to_string(), host);
+            }
+        }
+
+        let network = file_config.network.unwrap_or_default();
+        let proxy = match &network.proxy {
+            Some(url) => {
+                let proxy = Proxy::all(url).with_context(|| format!("invalid proxy: {}", url))?;
+                let no_proxy = network
+                    .no_proxy
+                    .as_ref()
+                    .and_then(|s| reqwest::NoProxy::from_string(s));
+                Some(proxy.no_proxy(no_proxy))
+            }
+            None => None,
+        };
+
+        let mut certificates = Vec::new();
+        for path in &network.ca_certificates {
+            let pem = std::fs::read(path)
+                .with_context(|| format!("failed to read certificate {}", path.display()))?;
+            let certs = Certificate::from_pem_bundle(&pem)
+                .with_context(|| format!("invalid certificate {}", path.display()))?;
+            if certs.is_empty() {
+                bail!("no certificates found in {}", path.display());
+            }
+            certificates.extend(certs);
+        }
+
+        let mut cache = file_config.cache.unwrap_or_default();
+        if cache.dir.is_none() {
+            cache.dir =
+                directories::BaseDirs::new().map(|dirs| dirs.cache_dir().join("devtoolinstaller"));
+        }
+
         Ok(Self {
             ensure_installed,
             package_configs,
             auth: file_config.auth,
+            credentials,
+            hosts,
+            github: file_config.github.unwrap_or_default(),
+            network,
+            mirrors: Mirrors::new(file_config.mirrors.unwrap_or_default()),
+            proxy,
+            certificates,
+            cache_dir: path.parent().map(|dir| dir.join("cache")),
+            cache,
+            offline: false,
         })
     }
 }
@@ -70,4 +375,9 @@ struct Packages {
 struct FileConfig {
     packages: Option<Packages>,
     auth: Option<Auth>,
+    github: Option<GitHubConfig>,
+    network: Option<NetworkConfig>,
+    hosts: Option<HashMap<String, HostConfig>>,
+    cache: Option<CacheConfig>,
+    mirrors: Option<HashMap<String, String>>,
 }
@@ -15,6 +15,19 @@ pub enum Error {
     /// the given package.
     MissingRelease,
     MissingProg(&'static str),
+    /// The downloaded asset didn't match the expected checksum.
+    ChecksumMismatch {
+        asset: String,
+        expected: String,
+        actual: String,
+    },
+    /// The package requires a checksum, but the release has none for the asset.
+    MissingChecksum(String),
+    /// The signature of the downloaded asset is missing or doesn't verify.
+    Signature {
+        asset: String,
+        reason: String,
+    },
 }
 
 impl fmt::Display for Error {
@@ -28,6 +41,25 @@ impl fmt::Display for Error {
             Error::External(reason) => write!(f, "{}", reason),
             Error::MissingSystemAsset => write!(f, "missing release asset for your system"),
             Error::MissingRelease => write!(f, "no release found"),
+            Error::ChecksumMismatch {
+                asset,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "checksum mismatch for {}: expected {}, got {}",
+                asset, expected, actual
+            ),
+            Error::MissingChecksum(asset) => {
+                write!(
+                    f,
+                    "no checksum published for {}, but one is required",
+                    asset
+                )
+            }
+            Error::Signature { asset, reason } => {
+                write!(f, "signature verification failed for {}: {}", asset, reason)
+            }
         }
     }
 }
@@ -0,0 +1,212 @@
+use crate::config::{self, Config, Host};
+use crate::github::try_get_tag;
+use crate::http::HttpClient;
+use crate::pkg::{Asset, Package, Release};
+use crate::source::{split_repo, ReleaseSource};
+use anyhow::{bail, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Resolves releases of repos on Gitea or Forgejo
+/// instances, such as Codeberg.
+pub struct GiteaClient {
+    http: Arc<HttpClient>,
+    hosts: HashMap<String, Host>,
+}
+
+impl GiteaClient {
+    pub fn new(cfg: &Config, http: Arc<HttpClient>) -> Self {
+        Self {
+            http,
+            hosts: cfg.hosts.clone(),
+        }
+    }
+
+    /// Gives the URL of the API of the package's repo, on the
+    /// instance given by the package or by the host of the repo.
+    fn repo_url(&self, pkg: &Package) -> String {
+        let (host, repo) = split_repo(pkg.repo(), config::CODEBERG);
+        let api_url = match (pkg.gitea(), self.hosts.get(host)) {
+            (Some(api_url), _) => api_url.trim_end_matches('/').to_string(),
+            (None, Some(host)) => host.api_url.clone(),
+            (None, None) => format!("https://{}/api/v1", host),
+        };
+        format!("{}/repos/{}", api_url, repo)
+    }
+
+    fn get_release(&self, url: &str) -> Result<Option<Release>> {
+        match self.http.get_json(url)? {
+            Some(body) => self.to_release(serde_json::from_str(&body)?).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn to_release(&self, release: GTRelease) -> Result<Release> {
+        let assets = release
+            .assets
+            .into_iter()
+            .map(|asset| Asset {
+                name: asset.name,
+                url: asset.browser_download_url,
+                sha256: None,
+            })
+            .collect();
+
+        Ok(Release {
+            name: release.name,
+            tag: try_get_tag(&release.tag_name),
+            prerelease: release.prerelease,
+            assets,
+        })
+    }
+}
+
+impl ReleaseSource for GiteaClient {
+    fn latest(&self, pkg: &Package) -> Result<Option<Release>> {
+        let url = format!("{}/releases/latest", self.repo_url(pkg));
+        self.get_release(&url)
+    }
+
+    fn get_from_tag(&self, pkg: &Package, tag: &str) -> Result<Option<Release>> {
+        let url = format!("{}/releases/tags/{}", self.repo_url(pkg), tag);
+        self.get_release(&url)
+    }
+
+    fn list(&self, pkg: &Package) -> Result<Vec<Release>> {
+        let url = format!("{}/releases?limit=50", self.repo_url(pkg));
+        let releases: Vec<GTRelease> = match self.http.get_json(&url)? {
+            Some(body) => serde_json::from_str(&body)?,
+            None => bail!("repository not found: {}", pkg.repo()),
+        };
+
+        releases
+            .into_iter()
+            .map(|release| self.to_release(release))
+            .collect()
+    }
+}
+
+#[derive(Deserialize)]
+struct GTRelease {
+    name: String,
+    tag_name: String,
+    prerelease: bool,
+    #[serde(default)]
+    assets: Vec<GTAsset>,
+}
+
+#[derive(Deserialize)]
+struct GTAsset {
+    name: String,
+    browser_download_url: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::HostKind;
+    use crate::http::testing::serve;
+    use crate::pkg::{CargoInstaller, GithubReleaseInstaller};
+    use crate::pkg_info;
+    use crate::source::Sources;
+
+    #[test]
+    fn test_list() -> Result<()> {
+        let (url, server) = serve(vec![(
+            "200 OK",
+            r#"[{
+                "id": 2,
+                "name": "v1.3.0-rc1",
+                "tag_name": "v1.3.0-rc1",
+                "prerelease": true,
+                "assets": []
+            }, {
+                "id": 1,
+                "name": "Tool v1.2.3",
+                "tag_name": "v1.2.3",
+                "prerelease": false,
+                "assets": [{
+                    "id": 10,
+                    "name": "tool-x86_64-linux.tar.gz",
+                    "size": 1024,
+                    "browser_download_url": "https://codeberg.org/owner/tool/releases/download/v1.2.3/tool-x86_64-linux.tar.gz"
+                }]
+            }]"#,
+        )])?;
+        let addr = url.trim_start_matches("http://").trim_end_matches('/');
+
+        let mut cfg = Config::default();
+        cfg.hosts.insert(
+            addr.to_string(),
+            Host {
+                kind: HostKind::Gitea,
+                api_url: format!("http://{}/api/v1", addr),
+                credentials: None,
+            },
+        );
+        let repo = format!("http://{}/owner/tool", addr);
+        let pkg = Package::new(
+            pkg_info!(repo, "tool"),
+            None,
+            Some(Box::new(CargoInstaller::default())),
+        );
+
+        let releases = Sources::new(&cfg).gitea.list(&pkg)?;
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].starts_with("GET /api/v1/repos/owner/tool/releases?limit=50 "));
+        assert_eq!(releases.len(), 2);
+        assert!(releases[0].prerelease);
+        assert_eq!(releases[1].tag, "v1.2.3");
+        assert_eq!(releases[1].assets[0].name, "tool-x86_64-linux.tar.gz");
+        Ok(())
+    }
+
+    #[test]
+    fn test_with_gitea() -> Result<()> {
+        let release = r#"{
+            "id": 1,
+            "name": "Tool v1.2.3",
+            "tag_name": "v1.2.3",
+            "prerelease": false,
+            "assets": [{
+                "id": 10,
+                "name": "tool-x86_64-linux.tar.gz",
+                "size": 1024,
+                "browser_download_url": "https://git.example.com/owner/tool/releases/download/v1.2.3/tool-x86_64-linux.tar.gz"
+            }]
+        }"#;
+        let (url, server) = serve(vec![("200 OK", release), ("200 OK", release)])?;
+
+        let cfg = Config::default();
+        let installer = GithubReleaseInstaller::new(
+            "tool-.*".into(),
+            Box::new(HttpClient::new(&cfg)),
+            Box::new(|_, _, _| Ok(())),
+        );
+        let pkg = Package::new(
+            pkg_info!("owner/tool", "tool"),
+            Some(Box::new(installer)),
+            None,
+        )
+        .with_gitea(&format!("{}api/v1/", url));
+
+        let sources = Sources::new(&cfg);
+        assert_eq!(sources.kind(&pkg), HostKind::Gitea);
+        assert!(!sources.is_github(&pkg));
+
+        let source = sources.get(&pkg);
+        let latest = source.latest(&pkg)?.expect("release");
+        let tagged = source.get_from_tag(&pkg, "v1.2.3")?.expect("release");
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].starts_with("GET /api/v1/repos/owner/tool/releases/latest "));
+        assert!(requests[1].starts_with("GET /api/v1/repos/owner/tool/releases/tags/v1.2.3 "));
+        for release in [latest, tagged] {
+            assert_eq!(release.tag, "v1.2.3");
+            assert_eq!(release.assets[0].name, "tool-x86_64-linux.tar.gz");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,107 @@
+use anyhow::{bail, Result};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Most repositories to query in a single request, keeping
+/// the query well below the node limit of the GraphQL API.
+pub const MAX_REPOS: usize = 50;
+
+/// Builds a query for the latest releases of the repos (`owner/name`),
+/// each aliased by its index, e.g. `r0: repository(...)`.
+pub fn latest_releases_query(repos: &[&str]) -> String {
+    let mut query = String::from("query {");
+    for (i, repo) in repos.iter().enumerate() {
+        let (owner, name) = repo.split_once('/').unwrap_or((repo, ""));
+        // JSON strings are valid GraphQL strings.
+        query.push_str(&format!(
+            " r{}: repository(owner: {}, name: {}) {{ latestRelease {{ tagName }} }}",
+            i,
+            Value::from(owner),
+            Value::from(name),
+        ));
+    }
+    query.push_str(" }");
+    query
+}
+
+#[derive(Deserialize)]
+struct Response {
+    data: Option<HashMap<String, Option<Repository>>>,
+    #[serde(default)]
+    errors: Vec<Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Repository {
+    latest_release: Option<LatestRelease>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LatestRelease {
+    tag_name: String,
+}
+
+/// Parses the response to a query of `count` repos, giving the tag
+/// of the latest release of each, in order. Repos that don't
+/// exist, or have no releases, have no tag.
+pub fn parse_latest_releases(body: &str, count: usize) -> Result<Vec<Option<String>>> {
+    let res: Response = serde_json::from_str(body)?;
+
+    let mut data = match res.data {
+        Some(data) => data,
+        None => bail!("GraphQL query failed: {}", Value::from(res.errors)),
+    };
+
+    // Repos not found are reported as errors, along with a null repository.
+    for err in &res.errors {
+        log::debug!("GraphQL error: {}", err);
+    }
+
+    Ok((0..count)
+        .map(|i| {
+            data.remove(&format!("r{}", i))
+                .flatten()
+                .and_then(|repo| repo.latest_release)
+                .map(|release| release.tag_name)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latest_releases_query() {
+        let query = latest_releases_query(&["rust-lang/rust-analyzer", "golang/tools"]);
+        assert_eq!(
+            query,
+            "query { r0: repository(owner: \"rust-lang\", name: \"rust-analyzer\") \
+             { latestRelease { tagName } } r1: repository(owner: \"golang\", name: \"tools\") \
+             { latestRelease { tagName } } }"
+        );
+    }
+
+    #[test]
+    fn test_parse_latest_releases() -> Result<()> {
+        let body = r#"{
+            "data": {
+                "r0": { "latestRelease": { "tagName": "2024-07-29" } },
+                "r1": { "latestRelease": null },
+                "r2": null
+            },
+            "errors": [{ "type": "NOT_FOUND", "path": ["r2"] }]
+        }"#;
+        assert_eq!(
+            parse_latest_releases(body, 3)?,
+            vec![Some("2024-07-29".to_string()), None, None]
+        );
+
+        let failed = r#"{ "errors": [{ "message": "Bad credentials" }] }"#;
+        assert!(parse_latest_releases(failed, 1).is_err());
+        Ok(())
+    }
+}
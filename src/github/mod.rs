@@ -0,0 +1,529 @@
+use crate::config::{self, Config, Credentials, Host, Resolve};
+use crate::http::{CachedResponse, HttpClient, NotCachedError, RateLimit, RateLimitError};
+use crate::pkg::{Asset, Package, Release, Version};
+use crate::source::{split_repo, ReleaseSource};
+use anyhow::{bail, Result};
+use lazy_static::lazy_static;
+use regex::Regex;
+use reqwest::{Method, StatusCode};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+mod graphql;
+mod web;
+
+lazy_static! {
+    static ref SEMVER: Regex = Regex::new(r"(v\d{1,2}\.\d{1,2}\.\d{1,3})").unwrap();
+    static ref DATE: Regex = Regex::new(r"^20\d\d-\d\d-\d\d$").unwrap();
+}
+
+pub struct GitHubClient {
+    http: Arc<HttpClient>,
+    /// GitHub hosts by name, including github.com.
+    hosts: HashMap<String, Host>,
+    /// How to resolve releases.
+    resolve: Resolve,
+}
+
+impl GitHubClient {
+    pub fn new(cfg: &Config, http: Arc<HttpClient>) -> Self {
+        Self {
+            http,
+            hosts: cfg.hosts.clone(),
+            resolve: cfg.github.resolve,
+        }
+    }
+
+    /// Gets the current rate limit of the core API.
+    /// Requesting it doesn't count against the limit.
+    pub fn rate_limit(&self) -> Result<RateLimit> {
+        let url = format!("{}/rate_limit", self.api_url(config::GITHUB));
+        let req = self.http.build_request(&url, "application/json")?;
+        let res = self.http.fetch(req)?;
+
+        if res.status != StatusCode::OK {
+            bail!("unexpected status code: {}", res.status);
+        }
+
+        let body: GHRateLimit = serde_json::from_slice(&res.body)?;
+        Ok(RateLimit {
+            limit: body.resources.core.limit,
+            remaining: body.resources.core.remaining,
+            reset: body.resources.core.reset,
+        })
+    }
+
+    fn get_release(&self, url: String) -> Result<Option<Release>> {
+        let release: GHRelease = match self.http.get_json(&url)? {
+            Some(body) => serde_json::from_str(&body)?,
+            None => return Ok(None),
+        };
+
+        self.to_release(release).map(Some)
+    }
+
+    fn to_release(&self, release: GHRelease) -> Result<Release> {
+        let tag_name = try_get_tag(&release.tag_name);
+        log::debug!("Found tag in release: {}", tag_name);
+
+        Ok(Release {
+            name: release.name,
+            tag: tag_name,
+            prerelease: release.prerelease,
+            assets: release.assets,
+        })
+    }
+
+    /// Gives the API URL of a host, GitHub Enterprise
+    /// hosts not configured use the default path.
+    fn api_url(&self, host: &str) -> String {
+        match self.hosts.get(host) {
+            Some(host) => host.api_url.clone(),
+            None => format!("https://{}/api/v3", host),
+        }
+    }
+
+    fn latest_url(&self, repo: &str) -> String {
+        let (host, repo) = split_repo(repo, config::GITHUB);
+        format!("{}/repos/{}/releases/latest", self.api_url(host), repo)
+    }
+
+    fn tag_url(&self, repo: &str, tag: &str) -> String {
+        let (host, repo) = split_repo(repo, config::GITHUB);
+        format!(
+            "{}/repos/{}/releases/tags/{}",
+            self.api_url(host),
+            repo,
+            tag
+        )
+    }
+
+    /// Gives the tags of the latest releases of the repos, querying
+    /// each host with the GraphQL API in as few requests as possible.
+    ///
+    /// Repos with a latest release cached within the TTL are served
+    /// from the cache. Only repos on hosts with a token can be queried,
+    /// as the GraphQL API requires one; the others, and those of requests
+    /// that failed, are left out, to be resolved using `latest` instead.
+    pub fn latest_tags(&self, repos: &[&str]) -> HashMap<String, Option<String>> {
+        let mut tags = HashMap::new();
+        if self.http.offline() {
+            return tags;
+        }
+
+        let mut by_host: HashMap<&str, Vec<&str>> = HashMap::new();
+        for repo in repos {
+            if let Some(tag) = self.cached_latest_tag(repo) {
+                log::debug!("Using cached latest release of {}", repo);
+                tags.insert(repo.to_string(), tag);
+                continue;
+            }
+            let (host, _) = split_repo(repo, config::GITHUB);
+            by_host.entry(host).or_default().push(repo);
+        }
+
+        for (host, repos) in by_host {
+            let url = self.graphql_url(host);
+            let url_host = reqwest::Url::parse(&url).ok();
+            let url_host = url_host.as_ref().and_then(|url| url.host_str());
+            if !matches!(
+                self.http.credentials(url_host),
+                Some(Credentials::Token { .. })
+            ) {
+                continue;
+            }
+
+            for chunk in repos.chunks(graphql::MAX_REPOS) {
+                let names: Vec<&str> = chunk
+                    .iter()
+                    .map(|repo| split_repo(repo, config::GITHUB).1)
+                    .collect();
+                match self.query_latest_tags(&url, &names) {
+                    Ok(found) => {
+                        for (repo, tag) in chunk.iter().zip(found) {
+                            self.cache_latest_tag(repo, &tag);
+                            tags.insert(repo.to_string(), tag);
+                        }
+                    }
+                    Err(err) => log::warn!("Failed to query latest releases on {}: {}", host, err),
+                }
+            }
+        }
+
+        tags
+    }
+
+    /// Gives the tag of the latest release of the repo if it's cached
+    /// within the TTL, either as the release itself or as a tag queried
+    /// earlier; `Some(None)` if the repo was found to have no release.
+    fn cached_latest_tag(&self, repo: &str) -> Option<Option<String>> {
+        let cache = self.http.cache()?;
+        if let Some(res) = cache.get_fresh(&self.latest_url(repo)) {
+            let release: GHRelease = serde_json::from_str(&res.body).ok()?;
+            return Some(Some(try_get_tag(&release.tag_name)));
+        }
+        let res = cache.get_fresh(&self.latest_tag_key(repo))?;
+        serde_json::from_str(&res.body).ok()
+    }
+
+    fn cache_latest_tag(&self, repo: &str, tag: &Option<String>) {
+        let Some(cache) = self.http.cache() else {
+            return;
+        };
+        let key = self.latest_tag_key(repo);
+        let res = CachedResponse::new(&key, None, None, serde_json::json!(tag).to_string());
+        if let Err(err) = cache.put(&res) {
+            log::warn!("Failed to cache latest release of {}: {}", repo, err);
+        }
+    }
+
+    /// Gives the key of the tag of the latest release of the repo as
+    /// queried using the GraphQL API, which has no URL of its own.
+    fn latest_tag_key(&self, repo: &str) -> String {
+        format!("{}#tag", self.latest_url(repo))
+    }
+
+    fn query_latest_tags(&self, url: &str, repos: &[&str]) -> Result<Vec<Option<String>>> {
+        let query = graphql::latest_releases_query(repos);
+        let body = serde_json::json!({ "query": query }).to_string();
+        let req = self
+            .http
+            .request(Method::POST, url, "application/json")?
+            .header("Content-Type", "application/json")
+            .body(body)
+            .build()?;
+
+        let res = self.http.fetch(req)?;
+        if res.status != StatusCode::OK {
+            bail!("unexpected status code: {}", res.status);
+        }
+
+        let tags = graphql::parse_latest_releases(std::str::from_utf8(&res.body)?, repos.len())?;
+        Ok(tags
+            .into_iter()
+            .map(|tag| tag.map(|tag| try_get_tag(&tag)))
+            .collect())
+    }
+
+    /// Gives the URL of the GraphQL API of a host, next to the REST API.
+    fn graphql_url(&self, host: &str) -> String {
+        let api_url = self.api_url(host);
+        match api_url.strip_suffix("/v3") {
+            // GitHub Enterprise: https://<host>/api/graphql
+            Some(base) => format!("{}/graphql", base),
+            None => format!("{}/graphql", api_url),
+        }
+    }
+
+    /// Gives the age of the cached latest release of the repo, if any.
+    pub fn latest_age(&self, repo: &str) -> Option<Duration> {
+        let cache = self.http.cache()?;
+        cache.get(&self.latest_url(repo)).map(|res| res.age())
+    }
+
+    /// Resolves a release using the API, or from the web pages of the
+    /// repo when configured to or when the API is rate limited.
+    /// `tags` are the tags to try, the latest release if none.
+    ///
+    /// Releases of packages that verify their assets are always resolved
+    /// using the API, since checksum and signature files aren't listed
+    /// on the web pages.
+    fn with_fallback<F>(&self, pkg: &Package, tags: &[&str], api: F) -> Result<Option<Release>>
+    where
+        F: FnOnce() -> Result<Option<Release>>,
+    {
+        let repo = pkg.repo().as_str();
+        let template = pkg.asset_template();
+        let web = !self.http.offline() && !pkg.verifies_assets();
+
+        if self.resolve == Resolve::Redirect && web {
+            return self.release_from_web(repo, template, tags);
+        }
+
+        match api() {
+            Err(err) if err.is::<RateLimitError>() && web => {
+                log::warn!("{}; resolving the release of {} without the API", err, repo);
+                self.release_from_web(repo, template, tags)
+            }
+            release => release,
+        }
+    }
+
+    /// Resolves a release from the web pages of the repo, following the
+    /// releases/latest redirect for the latest one. The assets aren't
+    /// listed there, so the only asset is the one named by the template.
+    fn release_from_web(
+        &self,
+        repo: &str,
+        template: Option<&str>,
+        tags: &[&str],
+    ) -> Result<Option<Release>> {
+        let repo_url = web::repo_url(repo);
+
+        let tag = if tags.is_empty() {
+            let url = format!("{}/releases/latest", repo_url);
+            self.http
+                .head(&url)?
+                .and_then(|url| web::tag_from_url(url.as_str()))
+        } else {
+            let mut found = None;
+            for tag in tags {
+                let url = format!("{}/releases/tag/{}", repo_url, tag);
+                if self.http.head(&url)?.is_some() {
+                    found = Some(tag.to_string());
+                    break;
+                }
+            }
+            found
+        };
+
+        let tag = match tag {
+            Some(tag) => tag,
+            None => return Ok(None),
+        };
+        log::debug!("Resolved tag {} of {} without the API", tag, repo);
+
+        let assets = match template {
+            Some(template) => {
+                let name = web::render_template(template, &tag);
+                let url = format!("{}/releases/download/{}/{}", repo_url, tag, name);
+                vec![Asset {
+                    name,
+                    url,
+                    sha256: None,
+                }]
+            }
+            None => {
+                log::warn!("No asset template for {}, its assets are unknown", repo);
+                Vec::new()
+            }
+        };
+
+        Ok(Some(Release {
+            name: tag.clone(),
+            tag: try_get_tag(&tag),
+            prerelease: false,
+            assets,
+        }))
+    }
+}
+
+impl ReleaseSource for GitHubClient {
+    fn latest(&self, pkg: &Package) -> Result<Option<Release>> {
+        let repo = pkg.repo().as_str();
+        self.with_fallback(pkg, &[], || self.get_release(self.latest_url(repo)))
+    }
+
+    fn get_from_tag(&self, pkg: &Package, tag: &str) -> Result<Option<Release>> {
+        let repo = pkg.repo().as_str();
+        self.with_fallback(pkg, &[tag], || self.get_release(self.tag_url(repo, tag)))
+    }
+
+    fn list(&self, pkg: &Package) -> Result<Vec<Release>> {
+        let (host, repo) = split_repo(pkg.repo(), config::GITHUB);
+        let url = format!(
+            "{}/repos/{}/releases?per_page=100",
+            self.api_url(host),
+            repo
+        );
+        let releases: Vec<GHRelease> = match self.http.get_json(&url)? {
+            Some(body) => serde_json::from_str(&body)?,
+            None => bail!("repository not found: {}", pkg.repo()),
+        };
+
+        releases
+            .into_iter()
+            .map(|release| self.to_release(release))
+            .collect()
+    }
+
+    fn try_get_release(&self, pkg: &Package, version: Option<Version>) -> Result<Option<Release>> {
+        let repo = pkg.repo().as_str();
+
+        let v = match &version {
+            Some(v) => v,
+            None => return self.latest(pkg),
+        };
+
+        let version = v.to_string();
+        if !matches!(v, Version::Sem(..)) {
+            return self.get_from_tag(pkg, &version);
+        }
+
+        // Not every project prefixes their tags with v.
+        let unprefixed = version.trim_start_matches('v');
+        let get = |tag| self.get_release(self.tag_url(repo, tag));
+        self.with_fallback(pkg, &[&version, unprefixed], || {
+            match get(&version) {
+                Ok(None) => get(unprefixed),
+                // Offline, only the other tag may be cached.
+                Err(err) if err.is::<NotCachedError>() => match get(unprefixed) {
+                    Ok(Some(release)) => Ok(Some(release)),
+                    _ => Err(err),
+                },
+                release => release,
+            }
+        })
+    }
+}
+
+/// Gives the version part of a tag, e.g. `v1.2.3` of `release-v1.2.3`.
+pub(crate) fn try_get_tag(tag: &str) -> String {
+    // Try semver
+    if let Some(matches) = SEMVER.captures(tag) {
+        if let Some(m) = matches.get(1) {
+            return m.as_str().to_string();
+        }
+    }
+
+    // Try date: yyyy-mm-dd
+    if DATE.is_match(tag) {
+        return tag.to_string();
+    }
+
+    tag.to_string()
+}
+
+#[derive(Clone, Deserialize)]
+struct GHRelease {
+    name: String,
+    tag_name: String,
+    prerelease: bool,
+    assets: Vec<Asset>,
+}
+
+#[derive(Deserialize)]
+struct GHRateLimit {
+    resources: GHRateLimitResources,
+}
+
+#[derive(Deserialize)]
+struct GHRateLimitResources {
+    core: GHRateLimitResource,
+}
+
+#[derive(Deserialize)]
+struct GHRateLimitResource {
+    limit: u64,
+    remaining: u64,
+    reset: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::HostKind;
+    use crate::http::testing::{serve, serve_raw};
+    use crate::source::Sources;
+
+    #[test]
+    fn test_latest_tags() -> Result<()> {
+        let (url, server) = serve(vec![(
+            "200 OK",
+            r#"{"data":{"r0":{"latestRelease":{"tagName":"v1.2.3"}},"r1":null}}"#,
+        )])?;
+        let addr = url.trim_start_matches("http://").trim_end_matches('/');
+
+        let auth: config::Auth = toml::from_str(r#"token = "token""#)?;
+        let mut cfg = Config::default();
+        cfg.hosts.insert(
+            addr.to_string(),
+            Host {
+                kind: HostKind::GitHub,
+                api_url: format!("http://{}/api/v3", addr),
+                credentials: Credentials::resolve(Some(&auth), addr, HostKind::GitHub),
+            },
+        );
+
+        let tool = format!("http://{}/a/tool", addr);
+        let missing = format!("http://{}/a/missing", addr);
+        let gh = Sources::new(&cfg).github;
+        let tags = gh.latest_tags(&[&tool, &missing, "a/unauthenticated"]);
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].starts_with("POST /api/graphql "));
+        assert!(requests[0]
+            .to_lowercase()
+            .contains("authorization: bearer token"));
+        assert_eq!(tags.get(&tool), Some(&Some("v1.2.3".to_string())));
+        assert_eq!(tags.get(&missing), Some(&None));
+        assert!(!tags.contains_key("a/unauthenticated"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_latest_tags_cached() -> Result<()> {
+        let (url, server) = serve(vec![(
+            "200 OK",
+            r#"{"data":{"r0":{"latestRelease":{"tagName":"v1.2.3"}}}}"#,
+        )])?;
+        let addr = url.trim_start_matches("http://").trim_end_matches('/');
+        let dir = tempfile::tempdir_in(".")?;
+
+        let auth: config::Auth = toml::from_str(r#"token = "token""#)?;
+        let mut cfg = Config {
+            cache_dir: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+        cfg.hosts.insert(
+            addr.to_string(),
+            Host {
+                kind: HostKind::GitHub,
+                api_url: format!("http://{}/api/v3", addr),
+                credentials: Credentials::resolve(Some(&auth), addr, HostKind::GitHub),
+            },
+        );
+
+        let tool = format!("http://{}/a/tool", addr);
+        let cached = format!("http://{}/a/cached", addr);
+        let gh = Sources::new(&cfg).github;
+        let latest = CachedResponse::new(
+            &gh.latest_url(&cached),
+            None,
+            None,
+            r#"{"name": "v2.0.0", "tag_name": "v2.0.0", "prerelease": false, "assets": []}"#
+                .to_string(),
+        );
+        gh.http.cache().expect("cache").put(&latest)?;
+
+        let tags = gh.latest_tags(&[&tool, &cached]);
+        let requests = server.join().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert!(!requests[0].contains("cached"));
+        assert_eq!(tags.get(&tool), Some(&Some("v1.2.3".to_string())));
+        assert_eq!(tags.get(&cached), Some(&Some("v2.0.0".to_string())));
+
+        // Both are cached now, so the server, which is gone, isn't queried.
+        assert_eq!(gh.latest_tags(&[&tool, &cached]), tags);
+        Ok(())
+    }
+
+    #[test]
+    fn test_release_from_web() -> Result<()> {
+        let (url, server) = serve_raw(vec![
+            b"HTTP/1.1 302 Found\r\nLocation: /a/tool/releases/tag/v1.2.3\r\n\
+              Content-Length: 0\r\nConnection: close\r\n\r\n"
+                .to_vec(),
+            b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_vec(),
+        ])?;
+        let repo = format!("{}a/tool", url);
+
+        let gh = Sources::new(&Config::default()).github;
+        let release = gh
+            .release_from_web(&repo, Some("tool-{version}-linux.tar.gz"), &[])?
+            .expect("release");
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].starts_with("HEAD /a/tool/releases/latest "));
+        assert!(requests[1].starts_with("HEAD /a/tool/releases/tag/v1.2.3 "));
+        assert_eq!(release.tag, "v1.2.3");
+        assert_eq!(release.assets[0].name, "tool-1.2.3-linux.tar.gz");
+        assert_eq!(
+            release.assets[0].url,
+            format!("{}/releases/download/v1.2.3/tool-1.2.3-linux.tar.gz", repo)
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,67 @@
+use crate::config;
+
+/// Gives the URL of the web page of a repo, e.g. `https://github.com/a/b`.
+pub fn repo_url(repo: &str) -> String {
+    let repo = repo.trim_end_matches('/').trim_end_matches(".git");
+    if repo.starts_with("https://") || repo.starts_with("http://") {
+        repo.to_string()
+    } else {
+        format!("https://{}/{}", config::GITHUB, repo)
+    }
+}
+
+/// Gives the tag of the release page that `releases/latest` redirected
+/// to, e.g. `https://github.com/a/b/releases/tag/v1.0.0`. Repos without
+/// releases redirect to the list of releases instead.
+pub fn tag_from_url(url: &str) -> Option<String> {
+    let (_, tag) = url.split_once("/releases/tag/")?;
+    let tag = tag.split(['?', '#']).next()?.trim_end_matches('/');
+    if tag.is_empty() {
+        None
+    } else {
+        Some(tag.to_string())
+    }
+}
+
+/// Gives the name of an asset from its template, replacing `{tag}`
+/// with the tag and `{version}` with the tag without a `v` prefix.
+pub fn render_template(template: &str, tag: &str) -> String {
+    let version = tag.strip_prefix('v').unwrap_or(tag);
+    template.replace("{tag}", tag).replace("{version}", version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repo_url() {
+        assert_eq!(repo_url("sharkdp/bat"), "https://github.com/sharkdp/bat");
+        assert_eq!(
+            repo_url("https://github.example.com/tools/lint.git"),
+            "https://github.example.com/tools/lint"
+        );
+    }
+
+    #[test]
+    fn test_tag_from_url() {
+        assert_eq!(
+            tag_from_url("https://github.com/sharkdp/bat/releases/tag/v0.24.0").as_deref(),
+            Some("v0.24.0")
+        );
+        assert_eq!(tag_from_url("https://github.com/a/b/releases"), None);
+    }
+
+    #[test]
+    fn test_render_template() {
+        let template = "lazygit_{version}_Linux_x86_64.tar.gz";
+        assert_eq!(
+            render_template(template, "v0.40.2"),
+            "lazygit_0.40.2_Linux_x86_64.tar.gz"
+        );
+        assert_eq!(
+            render_template("bat-{tag}-x86_64-unknown-linux-gnu.tar.gz", "v0.24.0"),
+            "bat-v0.24.0-x86_64-unknown-linux-gnu.tar.gz"
+        );
+    }
+}
@@ -0,0 +1,233 @@
+use crate::config::{self, Config, Host};
+use crate::github::try_get_tag;
+use crate::http::HttpClient;
+use crate::pkg::{Asset, Package, Release};
+use crate::source::ReleaseSource;
+use anyhow::{bail, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Resolves releases of projects on gitlab.com
+/// or self-hosted GitLab instances.
+pub struct GitLabClient {
+    http: Arc<HttpClient>,
+    hosts: HashMap<String, Host>,
+}
+
+impl GitLabClient {
+    pub fn new(cfg: &Config, http: Arc<HttpClient>) -> Self {
+        Self {
+            http,
+            hosts: cfg.hosts.clone(),
+        }
+    }
+
+    /// Gives the URL of the API of the project of a repo.
+    fn project_url(&self, repo: &str) -> String {
+        let (host, id) = split_project(repo);
+        let api_url = match self.hosts.get(host) {
+            Some(host) => host.api_url.clone(),
+            None => format!("https://{}/api/v4", host),
+        };
+        format!("{}/projects/{}", api_url, id)
+    }
+
+    fn get_release(&self, url: &str) -> Result<Option<Release>> {
+        match self.http.get_json(url)? {
+            Some(body) => self.to_release(serde_json::from_str(&body)?).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn to_release(&self, release: GLRelease) -> Result<Release> {
+        let assets = release
+            .assets
+            .links
+            .into_iter()
+            .map(|link| {
+                let url = link.direct_asset_url.unwrap_or(link.url);
+                // Links are named for display, e.g. "Linux (x86_64)",
+                // so the asset is named after the file it links to.
+                let name = match url.rsplit('/').next() {
+                    Some(file) if !file.is_empty() => file.to_string(),
+                    _ => link.name,
+                };
+                Asset {
+                    name,
+                    url,
+                    sha256: None,
+                }
+            })
+            .collect();
+
+        Ok(Release {
+            name: release.name.unwrap_or_else(|| release.tag_name.clone()),
+            tag: try_get_tag(&release.tag_name),
+            prerelease: release.upcoming_release,
+            assets,
+        })
+    }
+}
+
+impl ReleaseSource for GitLabClient {
+    fn latest(&self, pkg: &Package) -> Result<Option<Release>> {
+        let url = format!("{}/releases/permalink/latest", self.project_url(pkg.repo()));
+        self.get_release(&url)
+    }
+
+    fn get_from_tag(&self, pkg: &Package, tag: &str) -> Result<Option<Release>> {
+        let tag = tag.replace('/', "%2F");
+        let url = format!("{}/releases/{}", self.project_url(pkg.repo()), tag);
+        self.get_release(&url)
+    }
+
+    fn list(&self, pkg: &Package) -> Result<Vec<Release>> {
+        let url = format!("{}/releases?per_page=100", self.project_url(pkg.repo()));
+        let releases: Vec<GLRelease> = match self.http.get_json(&url)? {
+            Some(body) => serde_json::from_str(&body)?,
+            None => bail!("project not found: {}", pkg.repo()),
+        };
+
+        releases
+            .into_iter()
+            .map(|release| self.to_release(release))
+            .collect()
+    }
+}
+
+/// Splits a repo into its host and the ID of the project, which is
+/// either its URL encoded path, e.g. `group%2Fsubgroup%2Fproject` of
+/// `https://gitlab.com/group/subgroup/project`, or a numeric ID, e.g.
+/// of `https://gitlab.example.com/projects/42`. The host is gitlab.com
+/// unless given.
+fn split_project(repo: &str) -> (&str, String) {
+    let repo = repo.trim_end_matches('/').trim_end_matches(".git");
+    let (host, path) = match repo
+        .strip_prefix("https://")
+        .or_else(|| repo.strip_prefix("http://"))
+        .and_then(|rest| rest.split_once('/'))
+    {
+        Some((host, path)) => (host, path),
+        None => (config::GITLAB, repo),
+    };
+
+    // Pages of a project, e.g. its releases at /-/releases.
+    let path = path.split("/-/").next().unwrap_or(path);
+
+    let id = path
+        .trim_start_matches("api/v4/")
+        .strip_prefix("projects/")
+        .filter(|id| !id.is_empty() && id.chars().all(|c| c.is_ascii_digit()));
+
+    match id {
+        Some(id) => (host, id.to_string()),
+        None => (host, path.replace('/', "%2F")),
+    }
+}
+
+#[derive(Deserialize)]
+struct GLRelease {
+    name: Option<String>,
+    tag_name: String,
+    #[serde(default)]
+    upcoming_release: bool,
+    assets: GLAssets,
+}
+
+#[derive(Deserialize)]
+struct GLAssets {
+    #[serde(default)]
+    links: Vec<GLLink>,
+}
+
+#[derive(Deserialize)]
+struct GLLink {
+    name: String,
+    url: String,
+    direct_asset_url: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::HostKind;
+    use crate::http::testing::serve;
+    use crate::pkg::CargoInstaller;
+    use crate::pkg_info;
+    use crate::source::Sources;
+
+    #[test]
+    fn test_split_project() {
+        assert_eq!(
+            split_project("https://gitlab.com/group/sub/tool"),
+            ("gitlab.com", "group%2Fsub%2Ftool".to_string())
+        );
+        assert_eq!(
+            split_project("https://gitlab.com/group/tool/-/releases"),
+            ("gitlab.com", "group%2Ftool".to_string())
+        );
+        assert_eq!(
+            split_project("https://gitlab.example.com/projects/42"),
+            ("gitlab.example.com", "42".to_string())
+        );
+        assert_eq!(
+            split_project("https://gitlab.example.com/api/v4/projects/42"),
+            ("gitlab.example.com", "42".to_string())
+        );
+        assert_eq!(
+            split_project("group/tool"),
+            ("gitlab.com", "group%2Ftool".to_string())
+        );
+    }
+
+    #[test]
+    fn test_latest() -> Result<()> {
+        let (url, server) = serve(vec![(
+            "200 OK",
+            r#"{
+                "name": "Tool 1.2.3",
+                "tag_name": "v1.2.3",
+                "upcoming_release": false,
+                "assets": {
+                    "links": [{
+                        "name": "Linux (x86_64)",
+                        "url": "https://gitlab.com/group/tool/-/package_files/1/download",
+                        "direct_asset_url": "https://gitlab.com/group/tool/-/releases/v1.2.3/downloads/tool-linux.tar.gz"
+                    }],
+                    "sources": [{ "format": "zip", "url": "https://gitlab.com/group/tool.zip" }]
+                }
+            }"#,
+        )])?;
+        let addr = url.trim_start_matches("http://").trim_end_matches('/');
+
+        let mut cfg = Config::default();
+        cfg.hosts.insert(
+            addr.to_string(),
+            Host {
+                kind: HostKind::GitLab,
+                api_url: format!("http://{}/api/v4", addr),
+                credentials: None,
+            },
+        );
+        let repo = format!("http://{}/group/tool", addr);
+        let pkg = Package::new(
+            pkg_info!(repo, "tool"),
+            None,
+            Some(Box::new(CargoInstaller::default())),
+        );
+
+        let gl = Sources::new(&cfg).gitlab;
+        let release = gl.latest(&pkg)?.expect("release");
+
+        let requests = server.join().unwrap();
+        assert!(
+            requests[0].starts_with("GET /api/v4/projects/group%2Ftool/releases/permalink/latest ")
+        );
+        assert_eq!(release.tag, "v1.2.3");
+        assert_eq!(release.name, "Tool 1.2.3");
+        assert_eq!(release.assets.len(), 1);
+        assert_eq!(release.assets[0].name, "tool-linux.tar.gz");
+        Ok(())
+    }
+}
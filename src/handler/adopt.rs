@@ -0,0 +1,323 @@
+use super::{Context, Handler};
+use crate::pkg::{Binary, Entry, Package, Version};
+use crate::util;
+use anyhow::Result;
+use crossterm::style::Stylize;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::process::{self, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long a binary is given to print its version.
+const VERSION_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What was learned about a package found on disk.
+struct Found {
+    version: Version,
+    installer: Option<&'static str>,
+}
+
+impl Handler {
+    pub(super) fn handle_adopt(&self, cx: &mut Context) -> Result<()> {
+        let mut names: Vec<&String> = cx.packages.keys().collect();
+        names.sort();
+
+        let mut count = 0;
+        for name in names {
+            if cx.manifest.installed(name) {
+                continue;
+            }
+
+            let pkg = cx.packages.get(name).unwrap();
+            let bin = self.bin_dir.join(pkg.bin_name());
+            let has_bin = bin.exists();
+
+            let found = match self.read_metadata(pkg) {
+                Some(found) => found,
+                None if has_bin => Found {
+                    version: version_from_binary(&bin).unwrap_or(unknown()),
+                    installer: None,
+                },
+                None => continue,
+            };
+
+            let mut entry = Entry::new(name.to_string(), found.version.clone());
+            if let Some(installer) = found.installer {
+                entry = entry.with_installer(installer.to_string());
+            }
+            if has_bin {
+                let sha256 = fs::read(&bin).ok().map(|bytes| util::sha256_hex(&bytes));
+                entry = entry.with_binaries(vec![Binary {
+                    name: pkg.bin_name().to_string(),
+                    sha256,
+                }]);
+            }
+
+            println!(
+                "{} {}: {}",
+                "adopted".green(),
+                name.as_str().bold(),
+                found.version
+            );
+            cx.manifest.upsert(entry);
+            count += 1;
+        }
+
+        let suffix = if count == 1 { "" } else { "s" };
+        println!("Adopted {} package{}.", count, suffix);
+        Ok(())
+    }
+
+    /// Looks for metadata left by package managers
+    /// that tells which version of the package is installed.
+    fn read_metadata(&self, pkg: &Package) -> Option<Found> {
+        let pkg_dir = self.pkg_dir.join(pkg.mod_name());
+
+        // npm: <pkg>/lib/node_modules/<module>/package.json
+        let package_json = pkg_dir
+            .join("lib")
+            .join("node_modules")
+            .join(pkg.mod_name())
+            .join("package.json");
+        if package_json.exists() {
+            let version = util::json_from_file::<PackageJson>(&package_json)
+                .ok()
+                .and_then(|p| Version::try_from(p.version.as_str()).ok())
+                .unwrap_or(unknown());
+            return Some(Found {
+                version,
+                installer: Some("npm"),
+            });
+        }
+
+        // pip: <pkg>/venv/lib/python3.x/site-packages/<module>-<version>.dist-info
+        let venv_lib = pkg_dir.join("venv").join("lib");
+        if venv_lib.exists() {
+            let version = dist_info_version(&venv_lib, pkg.mod_name()).unwrap_or(unknown());
+            return Some(Found {
+                version,
+                installer: Some("pip"),
+            });
+        }
+
+        let bin = self.bin_dir.join(pkg.bin_name());
+        if !bin.exists() {
+            return None;
+        }
+
+        // cargo: <root>/.crates2.json lists the binaries of each install
+        if let Some(version) = self.cargo_version(pkg.bin_name()) {
+            return Some(Found {
+                version,
+                installer: Some("Cargo"),
+            });
+        }
+
+        // go: the build info embedded in the binary
+        if let Some(version) = go_version(&bin) {
+            return Some(Found {
+                version,
+                installer: Some("Go"),
+            });
+        }
+
+        None
+    }
+
+    fn cargo_version(&self, bin_name: &str) -> Option<Version> {
+        let path = self.dirs.root_dir.join(".crates2.json");
+        let crates: Crates = util::json_from_file(&path).ok()?;
+
+        // Keys look like: fd-find 9.0.0 (registry+https://github.com/rust-lang/crates.io-index)
+        crates
+            .installs
+            .iter()
+            .find(|(_, install)| install.bins.iter().any(|b| b == bin_name))
+            .and_then(|(key, _)| key.split_whitespace().nth(1))
+            .and_then(|v| Version::try_from(v).ok())
+    }
+}
+
+#[derive(Deserialize)]
+struct PackageJson {
+    version: String,
+}
+
+#[derive(Deserialize)]
+struct Crates {
+    installs: HashMap<String, CrateInstall>,
+}
+
+#[derive(Deserialize)]
+struct CrateInstall {
+    #[serde(default)]
+    bins: Vec<String>,
+}
+
+fn unknown() -> Version {
+    Version::Unknown("unknown".to_string())
+}
+
+/// Finds the version in the name of the dist-info directory
+/// of a module in any site-packages below `venv_lib`.
+fn dist_info_version(venv_lib: &Path, module: &str) -> Option<Version> {
+    // Distribution names are normalized with underscores in dist-info.
+    let prefix = format!("{}-", module.replace('-', "_").to_lowercase());
+
+    for python in fs::read_dir(venv_lib).ok()?.flatten() {
+        let site_packages = python.path().join("site-packages");
+        let entries = match fs::read_dir(&site_packages) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_lowercase();
+            if let Some(rest) = name.strip_prefix(&prefix) {
+                if let Some(version) = rest.strip_suffix(".dist-info") {
+                    return Version::try_from(version).ok();
+                }
+            }
+        }
+    }
+
+    None
+}
+
+/// Reads the module version from the build info of a Go binary.
+fn go_version(bin: &Path) -> Option<Version> {
+    if util::require_command("go").is_err() {
+        return None;
+    }
+
+    let mut cmd = process::Command::new("go");
+    cmd.args(["version", "-m"]).arg(bin);
+    let output = output_with_timeout(cmd)?;
+
+    // The main module is on a line like: mod <path> <version> <sum>
+    output
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<&str>>())
+        .find(|fields| fields.first() == Some(&"mod"))
+        .and_then(|fields| fields.get(2).map(|v| v.to_string()))
+        .and_then(|v| Version::try_from(v.as_str()).ok())
+}
+
+/// Runs the binary with common version arguments
+/// and looks for a version in the output.
+fn version_from_binary(bin: &Path) -> Option<Version> {
+    for arg in ["--version", "version"] {
+        let mut cmd = process::Command::new(bin);
+        cmd.arg(arg);
+
+        if let Some(output) = output_with_timeout(cmd) {
+            if let Some(version) = parse_version(&output) {
+                return Some(version);
+            }
+        }
+    }
+
+    None
+}
+
+/// Gives the first semver or date like word in the text.
+fn parse_version(text: &str) -> Option<Version> {
+    text.split_whitespace()
+        .map(|word| word.trim_matches(|c: char| !c.is_ascii_alphanumeric()))
+        .filter_map(|word| Version::try_from(word).ok())
+        .find(|version| !matches!(version, Version::Unknown(_)))
+}
+
+/// Runs the command and gives its stdout, unless it fails
+/// or doesn't finish in time (e.g. a server waiting for input).
+fn output_with_timeout(mut cmd: process::Command) -> Option<String> {
+    cmd.stdin(Stdio::null());
+    cmd.stdout(Stdio::piped());
+    cmd.stderr(Stdio::null());
+
+    let mut child = cmd.spawn().ok()?;
+
+    // Reads stdout while waiting, as the command blocks once the pipe is full.
+    let mut stdout = child.stdout.take()?;
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut output = Vec::new();
+        let _ = stdout.read_to_end(&mut output);
+        let _ = tx.send(output);
+    });
+
+    let start = Instant::now();
+    loop {
+        match child.try_wait().ok()? {
+            Some(status) if status.success() => break,
+            Some(_) => return None,
+            None if start.elapsed() > VERSION_TIMEOUT => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+            None => thread::sleep(Duration::from_millis(50)),
+        }
+    }
+
+    // A process the command started in the background may keep the pipe open.
+    let output = rx
+        .recv_timeout(VERSION_TIMEOUT.saturating_sub(start.elapsed()))
+        .ok()?;
+    Some(String::from_utf8_lossy(&output).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_version() {
+        let cases = [
+            ("bat 0.24.0 (fc95468)", "v0.24.0"),
+            (
+                "golang.org/x/tools/gopls v0.16.1\n    build info",
+                "v0.16.1",
+            ),
+            ("rust-analyzer 2024-07-22", "2024-07-22"),
+        ];
+
+        for (output, expected) in cases {
+            let version = parse_version(output).expect("version");
+            assert_eq!(version.to_string(), expected);
+        }
+
+        assert!(parse_version("no version here").is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_output_with_timeout() {
+        // More than a pipe holds, which the command would block on.
+        let mut cmd = process::Command::new("sh");
+        cmd.args(["-c", "head -c 200000 /dev/zero; echo ' 1.2.3'"]);
+        let output = output_with_timeout(cmd).expect("output");
+        assert_eq!(output.len(), 200007);
+        assert_eq!(output.trim_start_matches('\0'), " 1.2.3\n");
+
+        let mut cmd = process::Command::new("sh");
+        cmd.args(["-c", "exit 1"]);
+        assert!(output_with_timeout(cmd).is_none());
+    }
+
+    #[test]
+    fn test_dist_info_version() -> Result<()> {
+        let dir = tempfile::tempdir_in(".")?;
+        let site_packages = dir.path().join("python3.12").join("site-packages");
+        fs::create_dir_all(site_packages.join("python_lsp_server-1.11.0.dist-info"))?;
+
+        let version = dist_info_version(dir.path(), "python-lsp-server").expect("version");
+        assert_eq!(version.to_string(), "v1.11.0");
+        Ok(())
+    }
+}
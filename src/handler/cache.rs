@@ -0,0 +1,67 @@
+use super::{Context, Handler};
+use crate::cache::DownloadCache;
+use crate::util;
+use anyhow::{bail, Result};
+use crossterm::style::Stylize;
+use prettytable::{format, row, Table};
+
+impl Handler {
+    pub(super) fn handle_cache_list(&self, cx: &Context) -> Result<()> {
+        let cache = download_cache(cx)?;
+        let entries = cache.entries();
+
+        if !entries.is_empty() {
+            let mut table = Table::new();
+            table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+
+            table.add_row(row![
+                "Asset".to_string().green().bold(),
+                "Size".to_string().green().bold(),
+                "Last used".to_string().green().bold(),
+                "SHA-256".to_string().green().bold(),
+            ]);
+
+            for entry in &entries {
+                table.add_row(row![
+                    entry.name(),
+                    util::format_size(entry.size),
+                    format!("{} ago", util::format_duration(entry.age())),
+                    entry.sha256.get(..12).unwrap_or(&entry.sha256).dark_grey(),
+                ]);
+            }
+
+            table.printstd();
+            println!();
+        }
+
+        let suffix = if entries.len() == 1 { "" } else { "s" };
+        println!(
+            "{} download{} cached in {}, {} of {} used.",
+            entries.len(),
+            suffix,
+            cache.dir().display(),
+            util::format_size(cache.size()),
+            util::format_size(cache.max_size())
+        );
+
+        Ok(())
+    }
+
+    pub(super) fn handle_cache_clear(&self, cx: &Context) -> Result<()> {
+        let cache = download_cache(cx)?;
+        let reclaimed = cache.clear()?;
+        println!(
+            "Cleared {}, reclaimed {}.",
+            cache.dir().display(),
+            util::format_size(reclaimed)
+        );
+        Ok(())
+    }
+}
+
+fn download_cache(cx: &Context) -> Result<DownloadCache> {
+    match DownloadCache::from_config(&cx.config) {
+        Some(cache) => Ok(cache),
+        None => bail!("no download cache directory: unable to resolve the user cache directory"),
+    }
+}
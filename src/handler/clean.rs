@@ -0,0 +1,95 @@
+use super::{Context, Handler};
+use crate::archive;
+use crate::cache::DownloadCache;
+use crate::util;
+use anyhow::Result;
+use crossterm::style::Stylize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+
+impl Handler {
+    /// Removes leftover downloads, orphaned package directories and
+    /// stale download cache files. There are no old versions to remove:
+    /// updates uninstall the previous version, and cached assets of old
+    /// releases are shared with other roots, so those are left to eviction.
+    pub(super) fn handle_clean(&self, cx: &Context, dry_run: bool) -> Result<()> {
+        // Package directories are named after either the
+        // module or the package, depending on the installer.
+        let mut in_use: HashSet<&str> = HashSet::new();
+        for entry in &cx.manifest.packages {
+            in_use.insert(&entry.name);
+            if let Some(pkg) = cx.packages.get(&entry.name) {
+                in_use.insert(pkg.mod_name());
+            }
+        }
+
+        let mut garbage: Vec<(PathBuf, &str)> = Vec::new();
+        if self.pkg_dir.exists() {
+            for dir in fs::read_dir(&self.pkg_dir)? {
+                let dir = dir?;
+                let name = dir.file_name().to_string_lossy().to_string();
+
+                if !in_use.contains(name.as_str()) {
+                    garbage.push((dir.path(), "orphaned package directory"));
+                    continue;
+                }
+
+                if !dir.file_type()?.is_dir() {
+                    continue;
+                }
+
+                for file in fs::read_dir(dir.path())? {
+                    let file = file?;
+                    let fname = file.file_name().to_string_lossy().to_string();
+                    if !file.file_type()?.is_file() {
+                        continue;
+                    }
+                    if archive::has_archive_suffix(&fname) {
+                        garbage.push((file.path(), "downloaded archive"));
+                    } else if fname.ends_with(".part") {
+                        garbage.push((file.path(), "partial download"));
+                    }
+                }
+            }
+        }
+
+        if let Some(cache) = DownloadCache::from_config(&cx.config) {
+            for path in cache.stale_files()? {
+                garbage.push((path, "stale download cache entry"));
+            }
+        }
+
+        let mut reclaimed = 0;
+        for (path, reason) in &garbage {
+            let size = util::disk_usage(path)?;
+            reclaimed += size;
+
+            println!(
+                "{} {} ({}, {})",
+                if dry_run { "would remove" } else { "removing" }.yellow(),
+                path.display(),
+                reason,
+                util::format_size(size)
+            );
+
+            if !dry_run {
+                if path.is_dir() {
+                    fs::remove_dir_all(path)?;
+                } else {
+                    fs::remove_file(path)?;
+                }
+            }
+        }
+
+        if garbage.is_empty() {
+            println!("Nothing to clean.");
+        } else if dry_run {
+            println!("Would reclaim {}.", util::format_size(reclaimed));
+        } else {
+            println!("Reclaimed {}.", util::format_size(reclaimed));
+        }
+
+        Ok(())
+    }
+}
@@ -1,14 +1,22 @@
 use crate::config::Config;
-use crate::github::GitHubClient;
-use crate::pkg::{Dirs, Entry, Manifest};
-use crate::pkg::{Package, Version};
+use crate::pkg::version::normalize_tag;
+use crate::pkg::{
+    AssetFetcher, Dirs, Entry, LockedAsset, LockedPackage, LockedSignature, Lockfile, Manifest,
+};
+use crate::pkg::{Binary, Channel, Package, Release, Toolset, Version};
+use crate::source::Sources;
 use crate::util;
 use crate::{cli::*, pkgs};
 use anyhow::{bail, Result};
 use crossterm::style::Stylize;
 use prettytable::{format, row, Table};
-use std::thread;
-use std::{fs, path::PathBuf};
+use std::path::{Path, PathBuf};
+use std::{fs, thread};
+
+mod adopt;
+mod cache;
+mod clean;
+mod verify;
 
 #[cfg(test)]
 mod tests;
@@ -25,7 +33,7 @@ struct Context {
     manifest: Manifest,
     config: Config,
     packages: pkgs::Packages,
-    gh: GitHubClient,
+    sources: Sources,
 }
 
 impl Handler {
@@ -51,15 +59,43 @@ impl Handler {
     }
 
     pub fn handle(&self, cli: Cli) -> Result<()> {
-        let mut cx = self.bootstrap()?;
+        // Adopting is the way to recover from a lost or corrupted manifest.
+        let recover = matches!(cli.command, Command::Adopt);
+        let mut cx = self.bootstrap_with(recover, cli.offline)?;
+
+        let skip_signatures = match &cli.command {
+            Command::Install(args) => args.skip_signatures,
+            Command::Update(args) => args.skip_signatures,
+            _ => false,
+        };
+        if skip_signatures {
+            eprintln!("warning: not verifying the signatures of release assets");
+            for pkg in cx.packages.values_mut() {
+                pkg.set_signing(None);
+            }
+        }
 
         match cli.command {
             Command::Info => self.handle_info(&cx)?,
             Command::Check(args) => self.handle_check(&cx, args)?,
             Command::List(args) => self.handle_list(&cx, args)?,
+            Command::Install(args) if args.locked => {
+                self.handle_install_locked(&mut cx, &args.lockfile)?
+            }
             Command::Install(args) => self.handle_install(&mut cx, args.name, args.version)?,
+            Command::Lock(args) => self.handle_lock(&cx, &args.lockfile)?,
+            Command::Export(args) => self.handle_export(&cx, &args.file)?,
+            Command::Import(args) => self.handle_import(&mut cx, &args.file, args.dry_run)?,
+            Command::Verify(args) => self.handle_verify(&mut cx, args.fix)?,
+            Command::Adopt => self.handle_adopt(&mut cx)?,
+            Command::Clean(args) => self.handle_clean(&cx, args.dry_run)?,
+            Command::Cache(args) => match args.command {
+                CacheCommand::List => self.handle_cache_list(&cx)?,
+                CacheCommand::Clear => self.handle_cache_clear(&cx)?,
+            },
             Command::Remove { name } => self.handle_uninstall(&mut cx, name)?,
             Command::Update(args) => self.handle_update(&mut cx, args.name, args.version)?,
+            Command::Versions(args) => self.handle_versions(&cx, &args.name)?,
         };
 
         self.write_manifest(&cx.manifest)
@@ -70,11 +106,29 @@ impl Handler {
         println!("  configuration:  {}", self.config_filepath.display());
         println!("  binaries:       {}", self.bin_dir.display());
         println!("  packages:       {}", self.pkg_dir.display());
+        if let Some(dir) = &cx.config.cache_dir {
+            println!("  cache:          {}", dir.display());
+        }
         println!();
 
         print_platform();
         println!();
 
+        match &cx.config.credentials {
+            Some(credentials) => println!("Authentication: {}", credentials.source()),
+            None => println!("Authentication: none"),
+        }
+        match cx.sources.github.rate_limit() {
+            Ok(limit) => println!(
+                "Rate limit:     {}/{} remaining, resets in {}",
+                limit.remaining,
+                limit.limit,
+                util::format_duration(limit.resets_in())
+            ),
+            Err(err) => println!("Rate limit:     unavailable ({})", err),
+        }
+        println!();
+
         let count = cx.manifest.installed_count();
         let suffix = if count == 1 { "" } else { "s" };
         println!("{} installed package{}.", count, suffix);
@@ -85,6 +139,18 @@ impl Handler {
     fn handle_check(&self, cx: &Context, _args: CheckArgs) -> Result<()> {
         let mut results: Vec<(bool, String)> = Vec::new();
 
+        // With a token, the latest releases are queried in a single
+        // round trip; the rest are resolved one by one in parallel.
+        let repos: Vec<&str> = cx
+            .manifest
+            .packages
+            .iter()
+            .filter_map(|entry| cx.packages.get(&entry.name))
+            .filter(|pkg| cx.sources.is_github(pkg))
+            .map(|pkg| pkg.repo().as_str())
+            .collect();
+        let tags = cx.sources.github.latest_tags(&repos);
+
         // Check for versions i parallel using a thread scope.
         thread::scope(|s| {
             let mut handles: Vec<thread::ScopedJoinHandle<(bool, String)>> = Vec::new();
@@ -95,40 +161,69 @@ impl Handler {
                     None => continue,
                 };
 
-                let h = s.spawn(|| match cx.gh.latest(pkg.repo()) {
-                    Ok(release) => match release {
-                        Some(release) => {
-                            let version = entry.version.to_string();
-                            if release.tag != version {
-                                let icon = "".yellow();
-                                let output = format!(
-                                    "{} {}: {}  {}",
-                                    icon,
-                                    entry.name.as_str().bold(),
-                                    version,
-                                    release.tag,
-                                );
-
-                                (false, output)
-                            } else {
-                                let icon = "".green();
-                                let output =
-                                    format!("{} {}: {}", icon, entry.name.as_str().bold(), version);
-                                (true, output)
+                let h = s.spawn(|| {
+                    // Only GitHub packages were queried in the batch, other
+                    // packages with the same repo may be resolved elsewhere.
+                    let batched = tags.get(pkg.repo()).filter(|_| cx.sources.is_github(pkg));
+                    let latest = match batched {
+                        Some(tag) => Ok(tag.clone()),
+                        None => cx
+                            .sources
+                            .get(pkg)
+                            .latest(pkg)
+                            .map(|release| release.map(|release| release.tag)),
+                    };
+
+                    let (ok, mut output) = match latest {
+                        Ok(tag) => match tag {
+                            Some(tag) => {
+                                // Registries publish versions without a `v` prefix.
+                                let version = entry.version.to_string();
+                                if normalize_tag(&tag) != normalize_tag(&version) {
+                                    let icon = "".yellow();
+                                    let output = format!(
+                                        "{} {}: {}  {}",
+                                        icon,
+                                        entry.name.as_str().bold(),
+                                        version,
+                                        tag,
+                                    );
+
+                                    (false, output)
+                                } else {
+                                    let icon = "".green();
+                                    let output = format!(
+                                        "{} {}: {}",
+                                        icon,
+                                        entry.name.as_str().bold(),
+                                        version
+                                    );
+                                    (true, output)
+                                }
                             }
+                            None => {
+                                let icon = "?".yellow();
+                                (
+                                    false,
+                                    format!("{} {}: unable resolve version", icon, pkg.name()),
+                                )
+                            }
+                        },
+                        Err(err) => (
+                            false,
+                            format!("error when resolving release for {}: {}", pkg.name(), err),
+                        ),
+                    };
+
+                    // Offline, the release data may be outdated.
+                    if cx.config.offline {
+                        if let Some(age) = cx.sources.github.latest_age(pkg.repo()) {
+                            let age = format!(" (cached {} ago)", util::format_duration(age));
+                            output.push_str(&age.dark_grey().to_string());
                         }
-                        None => {
-                            let icon = "?".yellow();
-                            (
-                                false,
-                                format!("{} {}: unable resolve version", icon, pkg.name()),
-                            )
-                        }
-                    },
-                    Err(err) => (
-                        false,
-                        format!("error when resolving release for {}: {}", pkg.name(), err),
-                    ),
+                    }
+
+                    (ok, output)
                 });
 
                 handles.push(h);
@@ -207,6 +302,30 @@ impl Handler {
         table.printstd();
     }
 
+    fn handle_versions(&self, cx: &Context, name: &str) -> Result<()> {
+        let pkg = match cx.packages.get(name) {
+            Some(pkg) => pkg,
+            None => bail!("unknown package: {}", name),
+        };
+
+        let installed = cx
+            .manifest
+            .get(name)
+            .map(|entry| normalize_tag(&entry.version.to_string()));
+        for release in cx.sources.get(pkg).list(pkg)? {
+            let mut line = release.tag.clone();
+            if release.prerelease {
+                line.push_str(&" (prerelease)".dark_grey().to_string());
+            }
+            if installed == Some(normalize_tag(&release.tag)) {
+                line = format!("{} {}", line.green(), "(installed)".green());
+            }
+            println!("{}", line);
+        }
+
+        Ok(())
+    }
+
     fn handle_install(
         &self,
         cx: &mut Context,
@@ -223,7 +342,7 @@ impl Handler {
 
             if !cx.manifest.installed(&name) {
                 println!("Installing {}...", pkg.name().as_str().green());
-                let version = self.install_pkg(&cx.gh, &mut cx.manifest, pkg, version)?;
+                let version = self.install_pkg(&cx.sources, &mut cx.manifest, pkg, version)?;
 
                 match version {
                     Version::Unknown(v) => println!("Installed unknown version {}.", v),
@@ -242,7 +361,7 @@ impl Handler {
 
     fn install_pkg(
         &self,
-        gh: &GitHubClient,
+        sources: &Sources,
         manifest: &mut Manifest,
         pkg: &Package,
         vrs: Option<String>,
@@ -252,12 +371,189 @@ impl Handler {
             None => None,
         };
 
-        let release = gh.try_get_release(pkg.repo(), vrs)?;
-        let version = pkg.install(release, &self.dirs)?;
+        let channel = if vrs.is_some() {
+            Channel::Pinned
+        } else {
+            Channel::Latest
+        };
+
+        let release = sources.get(pkg).try_get_release(pkg, vrs)?;
+        sources.http.ensure_cached(pkg, release.as_ref())?;
+        self.install_release(manifest, pkg, release, channel)
+    }
+
+    fn install_release(
+        &self,
+        manifest: &mut Manifest,
+        pkg: &Package,
+        release: Option<Release>,
+        channel: Channel,
+    ) -> Result<Version> {
+        let before = self.bin_dir_entries()?;
+        let installation = pkg.install(release, &self.dirs)?;
+
+        // Binaries added to the bin directory are considered
+        // part of the package, with the bin name as fallback
+        // for packages overwriting an existing file.
+        let mut names: Vec<String> = self
+            .bin_dir_entries()?
+            .into_iter()
+            .filter(|name| !before.contains(name))
+            .collect();
+        if names.is_empty() && self.bin_dir.join(pkg.bin_name()).exists() {
+            names.push(pkg.bin_name().to_string());
+        }
+
+        let mut binaries = Vec::new();
+        for name in names {
+            let sha256 = match fs::read(self.bin_dir.join(&name)) {
+                Ok(bytes) => Some(util::sha256_hex(&bytes)),
+                Err(_) => None,
+            };
+            binaries.push(Binary { name, sha256 });
+        }
+
+        let entry = Entry::new(pkg.name().to_string(), installation.version.clone())
+            .with_channel(channel)
+            .with_installer(installation.installer)
+            .with_binaries(binaries)
+            .with_checksum(installation.checksum.map(|c| c.to_string()));
 
-        let entry = Entry::new(pkg.name().to_string(), version.clone());
         manifest.upsert(entry);
-        Ok(version)
+        Ok(installation.version)
+    }
+
+    /// Gives the names of the files in the bin directory.
+    fn bin_dir_entries(&self) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        for entry in fs::read_dir(&self.bin_dir)? {
+            let entry = entry?;
+            if let Some(name) = entry.file_name().to_str() {
+                names.push(name.to_string());
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    fn handle_install_locked(&self, cx: &mut Context, path: &Path) -> Result<()> {
+        if !path.exists() {
+            bail!("lockfile not found: {}", path.display());
+        }
+
+        let lockfile: Lockfile = util::toml_from_file(path)?;
+        let platform = util::platform();
+
+        for locked in &lockfile.packages {
+            let pkg = match cx.packages.get(&locked.name) {
+                Some(pkg) => pkg,
+                None => bail!("unknown package in lockfile: {}", locked.name),
+            };
+
+            let release = locked.release(&platform)?;
+            let version = release.try_get_version()?;
+            cx.sources.http.ensure_cached(pkg, Some(&release))?;
+
+            match cx.manifest.get(&locked.name) {
+                Some(entry) if entry.version.to_string() == version.to_string() => {
+                    log::info!("{} already installed at locked version", locked.name);
+                    continue;
+                }
+                Some(_) => {
+                    print!("Updating {} to {}... ", locked.name, version);
+                    pkg.uninstall(&self.dirs)?;
+                }
+                None => print!("Installing {} {}... ", locked.name, version),
+            }
+
+            self.install_release(&mut cx.manifest, pkg, Some(release), Channel::Pinned)?;
+            println!("done");
+        }
+
+        Ok(())
+    }
+
+    fn handle_lock(&self, cx: &Context, path: &Path) -> Result<()> {
+        let names = match &cx.config.ensure_installed {
+            Some(names) if !names.is_empty() => names,
+            _ => bail!("no packages to lock: ensure-installed is empty"),
+        };
+
+        let mut lockfile = if path.exists() {
+            util::toml_from_file(path)?
+        } else {
+            Lockfile::default()
+        };
+
+        let platform = util::platform();
+
+        // Assets are only downloaded to compute their checksums.
+        let downloads = tempfile::tempdir()?;
+        for name in names {
+            let pkg = match cx.packages.get(name) {
+                Some(pkg) => pkg,
+                None => bail!("unknown package from ensure installed: {}", name),
+            };
+
+            let version = cx
+                .config
+                .package_configs
+                .get(name)
+                .and_then(|cfg| cfg.version.clone());
+
+            let release = match cx.sources.get(pkg).try_get_release(pkg, version)? {
+                Some(release) => release,
+                None => bail!("no release found for {}", name),
+            };
+
+            let mut locked = LockedPackage::new(name.to_string(), release.tag.clone());
+            if let Some(asset) = pkg.find_asset(&release) {
+                log::info!("Downloading {} to compute checksum", asset.name);
+                let dest = downloads.path().join(&asset.name);
+                let sha256 = cx.sources.http.download(asset, &dest)?;
+                fs::remove_file(&dest)?;
+
+                // The signature is locked too, since a locked release
+                // holds nothing but what is in the lockfile.
+                let signature = match pkg.signing() {
+                    Some(signing) => match signing.find_signature(&release, asset) {
+                        Some(file) => {
+                            let dest = downloads.path().join(&file.name);
+                            let sha256 = cx.sources.http.download(file, &dest)?;
+                            fs::remove_file(&dest)?;
+                            Some(LockedSignature {
+                                name: file.name.clone(),
+                                url: file.url.clone(),
+                                sha256,
+                            })
+                        }
+                        None => bail!(
+                            "no {} signature published for {}",
+                            signing.scheme(),
+                            asset.name
+                        ),
+                    },
+                    None => None,
+                };
+
+                locked.assets.push(LockedAsset {
+                    platform: platform.clone(),
+                    name: asset.name.clone(),
+                    url: asset.url.clone(),
+                    sha256,
+                    signature,
+                });
+            }
+
+            println!("Locked {} to {}", name.as_str().bold(), release.tag);
+            lockfile.upsert(locked);
+        }
+
+        let content = toml::to_string_pretty(&lockfile)?;
+        util::write_file(path, content.as_bytes())?;
+
+        log::info!("Successfully wrote lockfile at {:?}", path);
+        Ok(())
     }
 
     fn ensure_install(&self, cx: &mut Context) -> Result<()> {
@@ -275,7 +571,7 @@ impl Handler {
                     );
 
                     print!("Installing {}... ", name);
-                    let version = self.install_pkg(&cx.gh, &mut cx.manifest, pkg, None)?;
+                    let version = self.install_pkg(&cx.sources, &mut cx.manifest, pkg, None)?;
                     println!("Installed version {}", version);
                 }
             }
@@ -317,17 +613,102 @@ impl Handler {
             None => None,
         };
 
-        let release = cx.gh.try_get_release(pkg.repo(), version)?;
+        let channel = if version.is_some() {
+            Channel::Pinned
+        } else {
+            Channel::Latest
+        };
 
-        let version = pkg.update(release, &self.dirs)?;
-        let entry = Entry::new(pkg.name().to_string(), version.clone());
-        cx.manifest.upsert(entry);
+        let release = cx.sources.get(pkg).try_get_release(pkg, version)?;
+        cx.sources.http.ensure_cached(pkg, release.as_ref())?;
+
+        pkg.uninstall(&self.dirs)?;
+        let version = self.install_release(&mut cx.manifest, pkg, release, channel)?;
 
         println!("Updated {} to version {}", name, version);
         Ok(())
     }
 
+    fn handle_export(&self, cx: &Context, path: &Path) -> Result<()> {
+        let toolset = Toolset::from(&cx.manifest);
+        let content = toml::to_string_pretty(&toolset)?;
+        util::write_file(path, content.as_bytes())?;
+
+        let count = toolset.packages.len();
+        let suffix = if count == 1 { "" } else { "s" };
+        println!("Exported {} package{} to {}", count, suffix, path.display());
+        Ok(())
+    }
+
+    fn handle_import(&self, cx: &mut Context, path: &Path, dry_run: bool) -> Result<()> {
+        let toolset: Toolset = util::toml_from_file(path)?;
+
+        let mut actions: Vec<(&Package, Option<Version>, Version, Channel)> = Vec::new();
+        for entry in &toolset.packages {
+            let pkg = match cx.packages.get(&entry.name) {
+                Some(pkg) => pkg,
+                None => {
+                    println!("{} {}: unknown package", "skip".dark_grey(), entry.name);
+                    continue;
+                }
+            };
+
+            // Other versions that aren't semantic versions or dates, e.g.
+            // `2024.8.6` on PyPI, are installed as given.
+            if matches!(&entry.version, Version::Unknown(v) if v == "unknown") {
+                println!("{} {}: unknown version", "skip".dark_grey(), entry.name);
+                continue;
+            }
+
+            let installed = cx.manifest.get(&entry.name).map(|e| e.version.clone());
+            match &installed {
+                Some(current) if current.to_string() == entry.version.to_string() => {
+                    println!("{} {}: {}", "keep".green(), entry.name, current);
+                    continue;
+                }
+                Some(current) => println!(
+                    "{} {}: {}  {}",
+                    "update".yellow(),
+                    entry.name,
+                    current,
+                    entry.version
+                ),
+                None => println!("{} {}: {}", "install".yellow(), entry.name, entry.version),
+            }
+
+            actions.push((pkg, installed, entry.version.clone(), entry.channel));
+        }
+
+        if dry_run || actions.is_empty() {
+            return Ok(());
+        }
+
+        println!();
+        for (pkg, installed, version, channel) in actions {
+            print!("Installing {} {}... ", pkg.name(), version);
+            let release = cx.sources.get(pkg).try_get_release(pkg, Some(version))?;
+            cx.sources.http.ensure_cached(pkg, release.as_ref())?;
+
+            if installed.is_some() {
+                pkg.uninstall(&self.dirs)?;
+            }
+
+            self.install_release(&mut cx.manifest, pkg, release, channel)?;
+            println!("done");
+        }
+
+        Ok(())
+    }
+
+    #[cfg(test)]
     fn bootstrap(&self) -> Result<Context> {
+        self.bootstrap_with(false, false)
+    }
+
+    /// Bootstraps the context. If `recover` is set, a manifest
+    /// that can't be read is backed up and replaced by an empty one.
+    /// If `offline` is set, nothing is fetched from the network.
+    fn bootstrap_with(&self, recover: bool, offline: bool) -> Result<Context> {
         if !self.bin_dir.exists() {
             fs::create_dir_all(&self.bin_dir)?;
         }
@@ -339,17 +720,30 @@ impl Handler {
             manifest
         } else {
             log::debug!("Loading manifest from {:?}", self.manifest_path);
-            let manifest: Manifest = util::json_from_file(&self.manifest_path)?;
-            manifest
+            match util::json_from_file(&self.manifest_path) {
+                Ok(manifest) => manifest,
+                Err(err) if recover => {
+                    let backup = self.manifest_path.with_extension("json.bak");
+                    eprintln!(
+                        "Unable to read manifest ({}), backing it up to {}",
+                        err,
+                        backup.display()
+                    );
+                    fs::rename(&self.manifest_path, &backup)?;
+                    Manifest::default()
+                }
+                Err(err) => bail!("failed to read manifest: {}", err),
+            }
         };
 
-        let config = Config::load_or_default(&self.config_filepath)?;
+        let mut config = Config::load_or_default(&self.config_filepath)?;
+        config.offline = offline;
         let packages = pkgs::get_packages(&config)?;
 
-        let gh = GitHubClient::new(&config);
+        let sources = Sources::new(&config);
 
         Ok(Context {
-            gh,
+            sources,
             manifest,
             config,
             packages,
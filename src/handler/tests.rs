@@ -1,6 +1,10 @@
 use super::{pkgs::get_packages, Handler};
 use crate::config::Config;
+use crate::http::NotCachedError;
+use crate::pkg::{Entry, Version};
+use crate::util;
 use anyhow::Result;
+use std::fs;
 use std::path::PathBuf;
 
 #[test]
@@ -26,6 +30,15 @@ fn test_handle_info() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_cache_dir() -> Result<()> {
+    let tx = TestContext::new();
+    let cx = tx.handler.bootstrap()?;
+    let dir = cx.config.cache.dir.expect("cache dir");
+    assert!(dir.starts_with(&tx.handler.dirs.root_dir));
+    Ok(())
+}
+
 #[test]
 fn test_install_go_package() -> Result<()> {
     let tx = TestContext::new();
@@ -44,6 +57,76 @@ fn test_install_release_asset_package() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_install_offline_not_cached() -> Result<()> {
+    let tx = TestContext::new();
+    let mut cx = tx.handler.bootstrap_with(false, true)?;
+    let err = tx
+        .handler
+        .handle_install(&mut cx, Some(String::from("rust-analyzer")), None)
+        .expect_err("nothing is cached");
+    assert!(err.is::<NotCachedError>());
+    assert!(!cx.manifest.installed("rust-analyzer"));
+    Ok(())
+}
+
+#[test]
+fn test_verify_fix() -> Result<()> {
+    let tx = TestContext::new();
+    let mut cx = tx.handler.bootstrap()?;
+    cx.manifest
+        .upsert(Entry::new("not-a-package".into(), Version::Sem(1, 0, 0)));
+
+    let link = tx.handler.bin_dir.join("dangling");
+    util::symlink(&tx.handler.pkg_dir.join("missing"), &link)?;
+
+    tx.handler.handle_verify(&mut cx, true)?;
+    assert!(!cx.manifest.installed("not-a-package"));
+    assert!(fs::symlink_metadata(&link).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_clean() -> Result<()> {
+    let tx = TestContext::new();
+    let mut cx = tx.handler.bootstrap()?;
+    cx.manifest.upsert(Entry::new(
+        "rust-analyzer".into(),
+        Version::Date(2024, 7, 22),
+    ));
+
+    let installed = tx.handler.pkg_dir.join("rust-analyzer");
+    let orphan = tx.handler.pkg_dir.join("removed-package");
+    fs::create_dir_all(&installed)?;
+    fs::create_dir_all(&orphan)?;
+    util::write_file(&installed.join("rust-analyzer.gz"), b"archive")?;
+    util::write_file(&installed.join("keep"), b"data")?;
+    util::write_file(&installed.join("rust-analyzer.gz.part"), b"arch")?;
+
+    // An asset no entry of the download cache refers to.
+    let blobs = cx.config.cache.dir.clone().unwrap().join("blobs");
+    let blob = blobs.join(util::sha256_hex(b"asset"));
+    fs::create_dir_all(&blobs)?;
+    util::write_file(&blob, b"asset")?;
+    let old = std::time::SystemTime::now() - std::time::Duration::from_secs(24 * 60 * 60);
+    fs::File::options()
+        .write(true)
+        .open(&blob)?
+        .set_modified(old)?;
+
+    tx.handler.handle_clean(&cx, true)?;
+    assert!(orphan.exists());
+    assert!(blob.exists());
+
+    tx.handler.handle_clean(&cx, false)?;
+    assert!(!orphan.exists());
+    assert!(!installed.join("rust-analyzer.gz").exists());
+    assert!(!installed.join("rust-analyzer.gz.part").exists());
+    assert!(installed.join("keep").exists());
+    assert!(!blob.exists());
+    Ok(())
+}
+
 struct TestContext {
     handler: Handler,
     _dir: tempfile::TempDir,
@@ -53,6 +136,13 @@ impl TestContext {
     fn new() -> Self {
         let dir = tempfile::tempdir_in(".").expect("failed to create tempdir");
         let handler = Handler::new(PathBuf::from(dir.path()));
+
+        // Keeps the download cache out of the user cache directory.
+        let downloads = dir.path().join("downloads");
+        let config = format!("[cache]\ndir = {:?}\n", downloads);
+        util::write_file(&handler.config_filepath, config.as_bytes())
+            .expect("failed to write config");
+
         Self { handler, _dir: dir }
     }
 }
@@ -0,0 +1,200 @@
+use super::{Context, Handler};
+use crate::pkg::{Binary, Version};
+use crate::util;
+use anyhow::Result;
+use crossterm::style::Stylize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Something wrong with an installed binary.
+#[derive(PartialEq)]
+enum Problem {
+    Missing,
+    BrokenLink,
+    NotExecutable,
+    Modified,
+}
+
+impl Problem {
+    fn describe(&self) -> &'static str {
+        match self {
+            Problem::Missing => "missing",
+            Problem::BrokenLink => "broken symlink",
+            Problem::NotExecutable => "not executable",
+            Problem::Modified => "modified since installation",
+        }
+    }
+}
+
+impl Handler {
+    pub(super) fn handle_verify(&self, cx: &mut Context, fix: bool) -> Result<()> {
+        let fail_icon = "".red();
+
+        // Names of all binaries that belong to some package.
+        let mut tracked: HashSet<String> = HashSet::new();
+        // Entries of packages dti doesn't know about.
+        let mut stale: Vec<String> = Vec::new();
+        // Entries that needs to be reinstalled, and if nothing of it remains.
+        let mut broken: Vec<(String, bool)> = Vec::new();
+        // Binaries that only lack the executable bit.
+        let mut not_executable: Vec<PathBuf> = Vec::new();
+
+        for entry in &cx.manifest.packages {
+            let pkg = cx.packages.get(&entry.name);
+            let binaries = match pkg {
+                // Entries written by older versions have no binaries recorded.
+                Some(pkg) if entry.binaries.is_empty() => vec![Binary {
+                    name: pkg.bin_name().to_string(),
+                    sha256: None,
+                }],
+                _ => entry.binaries.clone(),
+            };
+            tracked.extend(binaries.iter().map(|b| b.name.clone()));
+
+            if pkg.is_none() {
+                println!(
+                    "{} {}: unknown package",
+                    fail_icon,
+                    entry.name.as_str().bold()
+                );
+                stale.push(entry.name.clone());
+                continue;
+            }
+
+            let problems: Vec<(&Binary, Problem)> = binaries
+                .iter()
+                .filter_map(|b| self.check_binary(b).map(|p| (b, p)))
+                .collect();
+
+            if problems.is_empty() {
+                println!("{} {}", "".green(), entry.name.as_str().bold());
+                continue;
+            }
+
+            for (binary, problem) in &problems {
+                println!(
+                    "{} {}: {} {}",
+                    fail_icon,
+                    entry.name.as_str().bold(),
+                    binary.name,
+                    problem.describe()
+                );
+            }
+
+            if problems.iter().all(|(_, p)| *p == Problem::NotExecutable) {
+                not_executable.extend(problems.iter().map(|(b, _)| self.bin_dir.join(&b.name)));
+            } else {
+                let gone = problems.len() == binaries.len()
+                    && problems
+                        .iter()
+                        .all(|(_, p)| matches!(p, Problem::Missing | Problem::BrokenLink));
+                broken.push((entry.name.clone(), gone));
+            }
+        }
+
+        let mut dangling: Vec<PathBuf> = Vec::new();
+        for name in self.bin_dir_entries()? {
+            if tracked.contains(&name) {
+                continue;
+            }
+
+            let path = self.bin_dir.join(&name);
+            if is_broken_link(&path) {
+                println!("{} {}: broken symlink", fail_icon, path.display());
+                dangling.push(path);
+            } else {
+                println!("{} {}: untracked file", "?".yellow(), path.display());
+            }
+        }
+
+        let count = stale.len() + broken.len() + not_executable.len() + dangling.len();
+        if count == 0 {
+            return Ok(());
+        }
+
+        if !fix {
+            println!();
+            println!("Found {} problem(s). Run 'verify --fix' to repair.", count);
+            return Ok(());
+        }
+
+        println!();
+        for name in stale {
+            println!("Dropping {} from manifest", name);
+            cx.manifest.remove(&name);
+        }
+
+        for path in not_executable {
+            println!("Making {} executable", path.display());
+            util::make_executable(&path)?;
+        }
+
+        for path in dangling {
+            println!("Removing {}", path.display());
+            fs::remove_file(&path)?;
+        }
+
+        for (name, gone) in broken {
+            let pkg = cx.packages.get(&name).unwrap();
+            let entry = cx.manifest.get(&name).unwrap();
+            let channel = entry.channel;
+            let version = match &entry.version {
+                Version::Unknown(_) => None,
+                v => Some(v.clone()),
+            };
+
+            print!("Reinstalling {}... ", name);
+            let result = cx
+                .sources
+                .get(pkg)
+                .try_get_release(pkg, version)
+                .and_then(|release| {
+                    cx.sources.http.ensure_cached(pkg, release.as_ref())?;
+                    pkg.uninstall(&self.dirs)?;
+                    self.install_release(&mut cx.manifest, pkg, release, channel)
+                });
+
+            match result {
+                Ok(version) => println!("installed version {}", version),
+                Err(err) if gone => {
+                    println!("failed: {}", err);
+                    println!("Dropping {} from manifest", name);
+                    cx.manifest.remove(&name);
+                }
+                Err(err) => println!("failed: {}", err),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_binary(&self, binary: &Binary) -> Option<Problem> {
+        let path = self.bin_dir.join(&binary.name);
+        if is_broken_link(&path) {
+            return Some(Problem::BrokenLink);
+        }
+        if !path.exists() {
+            return Some(Problem::Missing);
+        }
+        if !util::is_executable(&path) {
+            return Some(Problem::NotExecutable);
+        }
+
+        if let Some(expected) = &binary.sha256 {
+            match fs::read(&path) {
+                Ok(bytes) if util::sha256_hex(&bytes) == *expected => {}
+                _ => return Some(Problem::Modified),
+            }
+        }
+
+        None
+    }
+}
+
+fn is_broken_link(path: &Path) -> bool {
+    match fs::symlink_metadata(path) {
+        Ok(m) => m.file_type().is_symlink() && !path.exists(),
+        Err(_) => false,
+    }
+}
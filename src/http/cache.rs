@@ -0,0 +1,148 @@
+use crate::util;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::{fmt, fs};
+
+/// On-disk cache of API responses, used to avoid requests
+/// altogether within the TTL and to make conditional
+/// requests after it.
+pub struct HttpCache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct CachedResponse {
+    pub url: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// Unix time (seconds) when the response was fetched or last revalidated.
+    pub fetched_at: u64,
+    pub body: String,
+}
+
+impl CachedResponse {
+    pub fn new(
+        url: &str,
+        etag: Option<String>,
+        last_modified: Option<String>,
+        body: String,
+    ) -> Self {
+        Self {
+            url: url.to_string(),
+            etag,
+            last_modified,
+            fetched_at: now(),
+            body,
+        }
+    }
+
+    /// Gives the time since the response was fetched or revalidated.
+    pub fn age(&self) -> Duration {
+        Duration::from_secs(now().saturating_sub(self.fetched_at))
+    }
+
+    /// Marks the response as revalidated now.
+    pub fn touch(&mut self) {
+        self.fetched_at = now();
+    }
+}
+
+impl HttpCache {
+    pub fn new(dir: PathBuf, ttl: Duration) -> Self {
+        Self { dir, ttl }
+    }
+
+    pub fn get(&self, url: &str) -> Option<CachedResponse> {
+        let path = self.path(url);
+        if !path.exists() {
+            return None;
+        }
+
+        match util::json_from_file::<CachedResponse>(&path) {
+            Ok(res) if res.url == url => Some(res),
+            Ok(_) => None,
+            Err(err) => {
+                log::warn!("Ignoring unreadable cache entry {:?}: {}", path, err);
+                None
+            }
+        }
+    }
+
+    /// Gives the cached response if it's younger than the TTL.
+    pub fn get_fresh(&self, url: &str) -> Option<CachedResponse> {
+        self.get(url).filter(|res| res.age() < self.ttl)
+    }
+
+    pub fn put(&self, res: &CachedResponse) -> Result<()> {
+        if !self.dir.exists() {
+            fs::create_dir_all(&self.dir)?;
+        }
+
+        let bytes = serde_json::to_vec(res)?;
+        util::write_file(&self.path(&res.url), &bytes)
+    }
+
+    fn path(&self, url: &str) -> PathBuf {
+        let key = util::sha256_hex(url.as_bytes());
+        self.dir.join(format!("{}.json", key))
+    }
+}
+
+/// Returned in offline mode for requests that can't
+/// be answered from the cache.
+#[derive(Debug)]
+pub struct NotCachedError {
+    pub url: String,
+}
+
+impl NotCachedError {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for NotCachedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "not cached, unable to GET {} in offline mode", self.url)
+    }
+}
+
+impl std::error::Error for NotCachedError {}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_fresh() -> Result<()> {
+        let dir = tempfile::tempdir_in(".")?;
+        let url = "https://api.github.com/repos/a/b/releases/latest";
+
+        let cache = HttpCache::new(dir.path().to_path_buf(), Duration::from_secs(60));
+        assert!(cache.get(url).is_none());
+
+        let mut res = CachedResponse::new(url, Some("\"etag\"".into()), None, "{}".into());
+        cache.put(&res)?;
+        assert!(cache.get_fresh(url).is_some());
+
+        res.fetched_at -= 120;
+        cache.put(&res)?;
+        assert!(cache.get_fresh(url).is_none());
+
+        let stale = cache.get(url).expect("stale response");
+        assert_eq!(stale.etag.as_deref(), Some("\"etag\""));
+        Ok(())
+    }
+}
@@ -0,0 +1,572 @@
+use crate::cache::DownloadCache;
+use crate::config::{Config, Credentials, Host, HostKind, Mirrors};
+use crate::pkg::{Asset, AssetFetcher, Package, Release};
+use crate::progress::Progress;
+use crate::util;
+use anyhow::{bail, Context, Result};
+use reqwest::blocking::{Client, Request, RequestBuilder, Response};
+use reqwest::header::{HeaderMap, HeaderName};
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, RANGE};
+use reqwest::{Method, StatusCode};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+mod cache;
+mod ratelimit;
+mod retry;
+#[cfg(test)]
+pub(crate) mod testing;
+
+pub use cache::NotCachedError;
+pub(crate) use cache::{CachedResponse, HttpCache};
+pub use ratelimit::{RateLimit, RateLimitError};
+pub(crate) use retry::TransientError;
+
+/// Builds an HTTP client using the network configuration:
+/// timeouts, proxy and additional trusted certificates.
+pub fn client(cfg: &Config) -> Client {
+    let mut builder = Client::builder()
+        .connect_timeout(Duration::from_secs(cfg.network.connect_timeout))
+        .timeout(Duration::from_secs(cfg.network.read_timeout));
+
+    if let Some(proxy) = &cfg.proxy {
+        builder = builder.proxy(proxy.clone());
+    }
+
+    for cert in &cfg.certificates {
+        builder = builder.add_root_certificate(cert.clone());
+    }
+
+    builder.build().expect("failed to build HTTP client")
+}
+
+/// The HTTP client shared by every release source, for its caching of
+/// responses and downloads, retries, mirrors and credentials by host.
+pub struct HttpClient {
+    /// Configured hosts by name, for their credentials.
+    hosts: HashMap<String, Host>,
+    /// Rewrites of API and download URLs to mirrors.
+    mirrors: Mirrors,
+    client: Client,
+    cache: Option<HttpCache>,
+    downloads: Option<DownloadCache>,
+    /// Only use cached responses and downloads, never the network.
+    offline: bool,
+    /// Longest time to wait for the rate limit to reset
+    /// instead of failing.
+    rate_limit_wait: Duration,
+    /// The rate limit reported in the latest response.
+    rate_limit: Mutex<Option<RateLimit>>,
+    /// Number of times to retry a request that failed
+    /// with what's likely a temporary problem.
+    retries: u32,
+}
+
+impl HttpClient {
+    pub fn new(cfg: &Config) -> Self {
+        Self {
+            hosts: cfg.hosts.clone(),
+            mirrors: cfg.mirrors.clone(),
+            client: client(cfg),
+            cache: cfg.cache_dir.as_ref().map(|dir| {
+                HttpCache::new(dir.join("http"), Duration::from_secs(cfg.github.cache_ttl))
+            }),
+            downloads: DownloadCache::from_config(cfg),
+            offline: cfg.offline,
+            rate_limit_wait: Duration::from_secs(cfg.github.rate_limit_wait),
+            rate_limit: Mutex::new(None),
+            retries: cfg.network.retries,
+        }
+    }
+
+    /// Tells if only cached responses and downloads are used.
+    pub fn offline(&self) -> bool {
+        self.offline
+    }
+
+    /// Gives the cache of responses, if there is one.
+    pub(crate) fn cache(&self) -> Option<&HttpCache> {
+        self.cache.as_ref()
+    }
+
+    /// Executes the request. If it's rejected because of the rate limit,
+    /// and the limit resets within the configured wait, the request is
+    /// retried after waiting; otherwise a RateLimitError is returned.
+    pub(crate) fn execute(&self, req: Request) -> Result<Response> {
+        let retry = req.try_clone();
+        let res = self.execute_once(req)?;
+
+        let err = match self.rate_limit_error(&res) {
+            Some(err) => err,
+            None => return Ok(res),
+        };
+
+        match retry {
+            Some(req) if err.wait <= self.rate_limit_wait => {
+                eprintln!(
+                    "API rate limit of {} exceeded, waiting {} for it to reset...",
+                    req.url().host_str().unwrap_or_default(),
+                    util::format_duration(err.wait)
+                );
+                thread::sleep(err.wait + Duration::from_secs(1));
+
+                let res = self.execute_once(req)?;
+                match self.rate_limit_error(&res) {
+                    Some(err) => Err(err.into()),
+                    None => Ok(res),
+                }
+            }
+            _ => Err(err.into()),
+        }
+    }
+
+    /// Executes the request and reads the response body, retrying
+    /// transient failures (server errors, connection problems and
+    /// truncated bodies) with exponential backoff.
+    pub(crate) fn fetch(&self, req: Request) -> Result<Fetched> {
+        let (method, url) = (req.method().clone(), req.url().clone());
+        if self.offline {
+            return Err(NotCachedError::new(url.as_str()).into());
+        }
+
+        self.with_retries(&method, url.as_str(), || {
+            let req = req.try_clone().context("request can't be retried")?;
+            self.fetch_once(req)
+        })
+    }
+
+    /// Runs the request until it succeeds, retrying transient
+    /// failures with exponential backoff.
+    pub(crate) fn with_retries<T, F>(&self, method: &Method, url: &str, mut request: F) -> Result<T>
+    where
+        F: FnMut() -> Result<T>,
+    {
+        let mut retry = 0;
+
+        loop {
+            match request() {
+                Err(err) if retry < self.retries && retry::is_transient(&err) => {
+                    retry += 1;
+                    let delay = retry::backoff(retry);
+                    log::info!(
+                        "{} {} failed: {}; retry {}/{} in {:?}",
+                        method,
+                        url,
+                        err,
+                        retry,
+                        self.retries,
+                        delay
+                    );
+                    thread::sleep(delay);
+                }
+                res => return res,
+            }
+        }
+    }
+
+    fn fetch_once(&self, req: Request) -> Result<Fetched> {
+        let res = self.execute(req)?;
+
+        let status = res.status();
+        if status.is_server_error() {
+            return Err(TransientError(format!("unexpected status code: {}", status)).into());
+        }
+
+        let headers = res.headers().clone();
+        let expected = res.content_length();
+        let body = res.bytes()?.to_vec();
+
+        if let Some(expected) = expected {
+            if body.len() as u64 != expected {
+                let msg = format!("body truncated: got {} of {} bytes", body.len(), expected);
+                return Err(TransientError(msg).into());
+            }
+        }
+
+        Ok(Fetched {
+            status,
+            headers,
+            body,
+        })
+    }
+
+    fn execute_once(&self, req: Request) -> Result<Response> {
+        let res = self.client.execute(req)?;
+
+        if let Some(limit) = RateLimit::from_headers(res.headers()) {
+            log::debug!(
+                "Rate limit: {}/{} remaining, resets in {}",
+                limit.remaining,
+                limit.limit,
+                util::format_duration(limit.resets_in())
+            );
+            if let Ok(mut current) = self.rate_limit.lock() {
+                *current = Some(limit);
+            }
+        }
+
+        Ok(res)
+    }
+
+    fn rate_limit_error(&self, res: &Response) -> Option<RateLimitError> {
+        let authenticated = self.credentials(res.url().host_str()).is_some();
+        let err = RateLimitError::from_response(res.status(), res.headers(), authenticated)?;
+        log::warn!("Rate limited for GET {}: {}", res.url(), err);
+        Some(err)
+    }
+
+    /// Gets the JSON body at the URL, or None if not found.
+    ///
+    /// Responses are cached: a cached response within the TTL is used
+    /// as is, otherwise it's revalidated using a conditional request.
+    /// In offline mode any cached response is used, regardless of age.
+    pub fn get_json(&self, url: &str) -> Result<Option<String>> {
+        self.get_json_as(url, "application/json")
+    }
+
+    /// Gets the JSON body at the URL like `get_json`, accepting the
+    /// media type, e.g. a smaller variant of a document.
+    pub(crate) fn get_json_as(&self, url: &str, mime: &str) -> Result<Option<String>> {
+        if let Some(cache) = &self.cache {
+            let res = if self.offline {
+                cache.get(url)
+            } else {
+                cache.get_fresh(url)
+            };
+            if let Some(res) = res {
+                log::debug!("Using cached response for GET {}", url);
+                return Ok(Some(res.body));
+            }
+        }
+
+        let cached = self.cache.as_ref().and_then(|cache| cache.get(url));
+
+        let mut req = self.build_request(url, mime)?;
+        if let Some(cached) = &cached {
+            let headers = req.headers_mut();
+            if let Some(etag) = &cached.etag {
+                headers.insert(IF_NONE_MATCH, etag.parse()?);
+            }
+            if let Some(last_modified) = &cached.last_modified {
+                headers.insert(IF_MODIFIED_SINCE, last_modified.parse()?);
+            }
+        }
+
+        let res = self.fetch(req)?;
+        match res.status {
+            StatusCode::OK => {
+                log::debug!("200 OK for GET {}", url);
+
+                let etag = res.header(ETAG);
+                let last_modified = res.header(LAST_MODIFIED);
+
+                let body = String::from_utf8(res.body)?;
+                log::debug!("Response body: {}", body);
+
+                if let Some(cache) = &self.cache {
+                    let cached = CachedResponse::new(url, etag, last_modified, body.clone());
+                    if let Err(err) = cache.put(&cached) {
+                        log::warn!("Failed to cache response for GET {}: {}", url, err);
+                    }
+                }
+
+                Ok(Some(body))
+            }
+            StatusCode::NOT_MODIFIED if cached.is_some() => {
+                log::debug!("304 Not Modified for GET {}", url);
+
+                let mut cached = cached.unwrap();
+                cached.touch();
+                if let Some(cache) = &self.cache {
+                    if let Err(err) = cache.put(&cached) {
+                        log::warn!("Failed to cache response for GET {}: {}", url, err);
+                    }
+                }
+
+                Ok(Some(cached.body))
+            }
+            // The Go module proxy answers 410 Gone for modules it doesn't have.
+            StatusCode::NOT_FOUND | StatusCode::GONE => Ok(None),
+            s => {
+                log::warn!("Unexpected status code for GET {}: {}", url, s);
+                bail!("unexpected status code: {}", s)
+            }
+        }
+    }
+
+    pub(crate) fn build_request(&self, url: &str, mime: &str) -> Result<Request> {
+        let req = self.request(Method::GET, url, mime)?.build()?;
+        Ok(req)
+    }
+
+    /// Builds a request, to a mirror of the URL if there is one, with
+    /// the credentials of its host. Requests to GitHub hosts ask for
+    /// the version of the API the responses are parsed as.
+    pub(crate) fn request(&self, method: Method, url: &str, mime: &str) -> Result<RequestBuilder> {
+        let url = match self.mirrors.mirror(url) {
+            Some(mirror) => {
+                log::debug!("Using mirror {} for {}", mirror, url);
+                mirror
+            }
+            None => url.to_string(),
+        };
+        let url = url.as_str();
+
+        let req = self
+            .client
+            .request(method, url)
+            .header("User-Agent", "dev-tool-installer")
+            .header("Accept", mime);
+
+        let url_host = reqwest::Url::parse(url)?.host_str().map(|h| h.to_string());
+        let host = self.host(url_host.as_deref());
+        let req = match host.map(|host| host.kind) {
+            Some(HostKind::GitHub) => req.header("X-GitHub-Api-Version", "2022-11-28"),
+            _ => req,
+        };
+
+        let req = match host.and_then(|host| host.credentials.as_ref()) {
+            Some(Credentials::Token { token, .. }) => req.bearer_auth(token),
+            Some(Credentials::OAuthApp {
+                client_id,
+                client_secret,
+            }) => req.basic_auth(client_id, Some(client_secret)),
+            None => req,
+        };
+
+        Ok(req)
+    }
+
+    /// Gives the configured host of a URL, which is
+    /// either the host itself or the host of its API.
+    fn host(&self, url_host: Option<&str>) -> Option<&Host> {
+        let url_host = url_host?;
+        self.hosts
+            .iter()
+            .find(|(name, host)| {
+                name.as_str() == url_host
+                    || reqwest::Url::parse(&host.api_url)
+                        .ok()
+                        .is_some_and(|url| url.host_str() == Some(url_host))
+            })
+            .map(|(_, host)| host)
+    }
+
+    /// Gives the credentials to use for requests to the host of a URL.
+    pub(crate) fn credentials(&self, url_host: Option<&str>) -> Option<&Credentials> {
+        self.host(url_host)
+            .and_then(|host| host.credentials.as_ref())
+    }
+
+    /// Requests the head of a web page, following redirects.
+    /// Gives the final URL, or None if not found.
+    pub(crate) fn head(&self, url: &str) -> Result<Option<reqwest::Url>> {
+        if self.offline {
+            return Err(NotCachedError::new(url).into());
+        }
+
+        self.with_retries(&Method::HEAD, url, || {
+            let req = self.request(Method::HEAD, url, "text/html")?.build()?;
+            let res = self.execute(req)?;
+
+            let status = res.status();
+            if status.is_server_error() {
+                let msg = format!("unexpected status code: {}", status);
+                return Err(TransientError(msg).into());
+            }
+
+            match status {
+                s if s.is_success() => Ok(Some(res.url().clone())),
+                StatusCode::NOT_FOUND => Ok(None),
+                s => bail!("unexpected status code: HEAD {}: {}", url, s),
+            }
+        })
+    }
+
+    /// Checks that the package can be installed from the release.
+    /// In offline mode that's only the case when its asset has
+    /// been downloaded before; nothing is checked otherwise.
+    pub fn ensure_cached(&self, pkg: &Package, release: Option<&Release>) -> Result<()> {
+        if !self.offline {
+            return Ok(());
+        }
+
+        let asset = match release.and_then(|release| pkg.find_asset(release)) {
+            Some(asset) => asset,
+            None => bail!(
+                "unable to install {} in offline mode: no release asset for your system",
+                pkg.name()
+            ),
+        };
+
+        match &self.downloads {
+            Some(downloads) if downloads.contains(&asset.url, asset.sha256.as_deref()) => Ok(()),
+            _ => Err(NotCachedError::new(&asset.url).into()),
+        }
+    }
+
+    /// Streams the asset to the partial download at `part`, resuming
+    /// where an earlier attempt left off if the server supports it.
+    /// Gives the SHA-256 of the complete download.
+    fn download_once(&self, asset: &Asset, part: &Path) -> Result<String> {
+        let offset = fs::metadata(part).map(|m| m.len()).unwrap_or(0);
+
+        let mut req = self.build_request(&asset.url, "application/octet-stream")?;
+        if offset > 0 {
+            req.headers_mut()
+                .insert(RANGE, format!("bytes={}-", offset).parse()?);
+        }
+
+        let mut res = self.execute(req)?;
+        let status = res.status();
+        if status.is_server_error() {
+            return Err(TransientError(format!("unexpected status code: {}", status)).into());
+        }
+
+        let mut hasher = Sha256::new();
+        let (mut file, resumed) = match status {
+            StatusCode::PARTIAL_CONTENT if offset > 0 => {
+                log::info!("Resuming download of {} at {} bytes", asset.name, offset);
+                std::io::copy(&mut fs::File::open(part)?, &mut hasher)?;
+                (fs::OpenOptions::new().append(true).open(part)?, offset)
+            }
+            StatusCode::OK => (fs::File::create(part)?, 0),
+            // The partial download doesn't match the asset, start over.
+            StatusCode::RANGE_NOT_SATISFIABLE => {
+                fs::remove_file(part)?;
+                let msg = format!("unable to resume download of {}", asset.name);
+                return Err(TransientError(msg).into());
+            }
+            s => {
+                log::warn!("Unexpected status code: GET {}: {}", asset.url, s);
+                bail!("unexpected status code: GET {}: {}", asset.url, s);
+            }
+        };
+
+        let total = res.content_length().map(|len| len + resumed);
+        let mut progress = Progress::new(&asset.name, total, resumed);
+        let mut written = resumed;
+        let mut buf = vec![0; 64 * 1024];
+
+        loop {
+            let n = match res.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(err) => {
+                    let msg = format!("download of {} interrupted: {}", asset.name, err);
+                    return Err(TransientError(msg).into());
+                }
+            };
+
+            file.write_all(&buf[..n])?;
+            hasher.update(&buf[..n]);
+            written += n as u64;
+            progress.add(n as u64);
+        }
+        file.flush()?;
+
+        if let Some(total) = total {
+            if written != total {
+                let msg = format!("body truncated: got {} of {} bytes", written, total);
+                return Err(TransientError(msg).into());
+            }
+        }
+
+        progress.finish();
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+}
+
+/// A response with its body read.
+pub(crate) struct Fetched {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+impl Fetched {
+    pub fn header(&self, name: HeaderName) -> Option<String> {
+        self.headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string())
+    }
+}
+
+impl AssetFetcher for HttpClient {
+    fn download(&self, asset: &Asset, dest: &Path) -> Result<String> {
+        if let Some(downloads) = &self.downloads {
+            if let Some(entry) = downloads.get(&asset.url, asset.sha256.as_deref()) {
+                log::debug!("Using cached download for GET {}", asset.url);
+                fs::copy(downloads.path(&entry), dest)?;
+                return Ok(entry.sha256);
+            }
+        }
+
+        if self.offline {
+            return Err(NotCachedError::new(&asset.url).into());
+        }
+
+        // A partial download is kept, so that it can be
+        // resumed by the next attempt, even in a later run.
+        let part = part_path(dest);
+        let sha256 = self.with_retries(&Method::GET, &asset.url, || {
+            self.download_once(asset, &part)
+        })?;
+        fs::rename(&part, dest)?;
+
+        if let Some(downloads) = &self.downloads {
+            if let Err(err) = downloads.put(&asset.url, dest, &sha256) {
+                log::warn!("Failed to cache download of {}: {}", asset.url, err);
+            }
+        }
+
+        Ok(sha256)
+    }
+}
+
+fn part_path(dest: &Path) -> PathBuf {
+    let mut name = dest.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    dest.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use testing::serve_raw;
+
+    #[test]
+    fn test_download_resume() -> Result<()> {
+        // Cut off the first response halfway.
+        let (url, server) = serve_raw(vec![
+            b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\n01234".to_vec(),
+            b"HTTP/1.1 206 Partial Content\r\nContent-Length: 5\r\n\r\n56789".to_vec(),
+        ])?;
+        let url = format!("{}asset.tar.gz", url);
+
+        let dir = tempfile::tempdir_in(".")?;
+        let dest = dir.path().join("asset.tar.gz");
+        let asset = Asset {
+            name: "asset.tar.gz".into(),
+            url,
+            sha256: None,
+        };
+
+        let http = HttpClient::new(&Config::default());
+        let sha256 = http.download(&asset, &dest)?;
+
+        let requests = server.join().unwrap();
+        assert!(requests[1].to_lowercase().contains("range: bytes=5-"));
+        assert_eq!(fs::read(&dest)?, b"0123456789");
+        assert_eq!(sha256, util::sha256_hex(b"0123456789"));
+        assert!(!part_path(&dest).exists());
+        Ok(())
+    }
+}
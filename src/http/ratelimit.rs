@@ -0,0 +1,147 @@
+use crate::util;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::StatusCode;
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The rate limit state as reported by the GitHub API.
+#[derive(Clone, Copy, Debug)]
+pub struct RateLimit {
+    pub limit: u64,
+    pub remaining: u64,
+    /// Unix time (seconds) when the limit resets.
+    pub reset: u64,
+}
+
+impl RateLimit {
+    /// Reads the X-RateLimit-* headers of a response.
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        Some(Self {
+            limit: header_u64(headers, "x-ratelimit-limit")?,
+            remaining: header_u64(headers, "x-ratelimit-remaining")?,
+            reset: header_u64(headers, "x-ratelimit-reset")?,
+        })
+    }
+
+    /// Gives the time until the limit resets.
+    pub fn resets_in(&self) -> Duration {
+        Duration::from_secs(self.reset.saturating_sub(now()))
+    }
+}
+
+/// Returned when a request is rejected because
+/// of the primary or secondary rate limit.
+#[derive(Debug)]
+pub struct RateLimitError {
+    /// Time to wait before trying again.
+    pub wait: Duration,
+    pub authenticated: bool,
+}
+
+impl RateLimitError {
+    /// Checks if the response was rejected because of a rate limit.
+    pub fn from_response(
+        status: StatusCode,
+        headers: &HeaderMap,
+        authenticated: bool,
+    ) -> Option<Self> {
+        if status != StatusCode::FORBIDDEN && status != StatusCode::TOO_MANY_REQUESTS {
+            return None;
+        }
+
+        // Secondary rate limits tell how long to wait.
+        if let Some(secs) = header_u64(headers, RETRY_AFTER.as_str()) {
+            return Some(Self {
+                wait: Duration::from_secs(secs),
+                authenticated,
+            });
+        }
+
+        match RateLimit::from_headers(headers) {
+            Some(limit) if limit.remaining == 0 => Some(Self {
+                wait: limit.resets_in(),
+                authenticated,
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for RateLimitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "GitHub API rate limit exceeded, resets in {}",
+            util::format_duration(self.wait)
+        )?;
+
+        if !self.authenticated {
+            write!(
+                f,
+                " (authenticate to get a higher limit, e.g. by setting GITHUB_TOKEN)"
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for RateLimitError {}
+
+fn header_u64(headers: &HeaderMap, name: &str) -> Option<u64> {
+    headers.get(name)?.to_str().ok()?.trim().parse().ok()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&'static str, String)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, value.parse().unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn test_primary_limit() {
+        let reset = now() + 600;
+        let headers = headers(&[
+            ("x-ratelimit-limit", "60".into()),
+            ("x-ratelimit-remaining", "0".into()),
+            ("x-ratelimit-reset", reset.to_string()),
+        ]);
+
+        let err = RateLimitError::from_response(StatusCode::FORBIDDEN, &headers, false)
+            .expect("rate limit error");
+        assert!(err.wait > Duration::from_secs(590));
+        assert!(err.to_string().contains("GITHUB_TOKEN"));
+    }
+
+    #[test]
+    fn test_secondary_limit() {
+        let headers = headers(&[("retry-after", "30".into())]);
+        let err = RateLimitError::from_response(StatusCode::TOO_MANY_REQUESTS, &headers, true)
+            .expect("rate limit error");
+        assert_eq!(err.wait, Duration::from_secs(30));
+        assert!(!err.to_string().contains("GITHUB_TOKEN"));
+    }
+
+    #[test]
+    fn test_other_forbidden() {
+        let headers = headers(&[
+            ("x-ratelimit-limit", "60".into()),
+            ("x-ratelimit-remaining", "42".into()),
+            ("x-ratelimit-reset", now().to_string()),
+        ]);
+        assert!(RateLimitError::from_response(StatusCode::FORBIDDEN, &headers, false).is_none());
+    }
+}
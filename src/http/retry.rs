@@ -0,0 +1,67 @@
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// Delay before the first retry, doubled for every attempt after it.
+const BASE_DELAY: Duration = Duration::from_millis(500);
+const MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// A failure that is likely to go away if the request is retried,
+/// e.g. a 502 Bad Gateway or a download cut short.
+#[derive(Debug)]
+pub struct TransientError(pub String);
+
+impl fmt::Display for TransientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for TransientError {}
+
+/// Checks if a failed request is worth retrying.
+pub fn is_transient(err: &anyhow::Error) -> bool {
+    if err.downcast_ref::<TransientError>().is_some() {
+        return true;
+    }
+
+    match err.downcast_ref::<reqwest::Error>() {
+        Some(err) => err.is_connect() || err.is_timeout() || err.is_body() || err.is_request(),
+        None => false,
+    }
+}
+
+/// Gives the delay before the given retry (starting at 1):
+/// exponential backoff with jitter, so that clients failing
+/// at the same time don't retry at the same time.
+pub fn backoff(retry: u32) -> Duration {
+    let exp = BASE_DELAY.saturating_mul(1 << retry.saturating_sub(1).min(16));
+    let delay = exp.min(MAX_DELAY);
+
+    // Wait somewhere between half and all of the delay.
+    let jitter = RandomState::new().build_hasher().finish() % 1000;
+    delay / 2 + delay / 2 * jitter as u32 / 1000
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        for retry in 1..10 {
+            let delay = backoff(retry);
+            let max = BASE_DELAY.saturating_mul(1 << (retry - 1)).min(MAX_DELAY);
+            assert!(delay >= max / 2, "retry {}: {:?}", retry, delay);
+            assert!(delay <= max, "retry {}: {:?}", retry, delay);
+        }
+    }
+
+    #[test]
+    fn test_is_transient() {
+        let err = anyhow::Error::new(TransientError("502 Bad Gateway".into()));
+        assert!(is_transient(&err));
+        assert!(!is_transient(&anyhow::anyhow!("not found")));
+    }
+}
@@ -0,0 +1,72 @@
+use anyhow::Result;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+/// Serves the responses, of a status and body, to a request each,
+/// giving the URL of the server and the requests.
+pub(crate) fn serve(
+    responses: Vec<(&'static str, &'static str)>,
+) -> Result<(String, thread::JoinHandle<Vec<String>>)> {
+    let responses = responses
+        .into_iter()
+        .map(|(status, body)| {
+            format!(
+                "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            )
+            .into_bytes()
+        })
+        .collect();
+    serve_raw(responses)
+}
+
+/// Serves the raw responses to a request each, closing the connection
+/// after every response, so they can be cut off or carry any header.
+pub(crate) fn serve_raw(
+    responses: Vec<Vec<u8>>,
+) -> Result<(String, thread::JoinHandle<Vec<String>>)> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?;
+
+    let server = thread::spawn(move || {
+        let mut requests = Vec::new();
+        for res in responses {
+            let (mut stream, _) = listener.accept().unwrap();
+            requests.push(read_request(&mut stream));
+            stream.write_all(&res).unwrap();
+        }
+        requests
+    });
+
+    Ok((format!("http://{}/", addr), server))
+}
+
+/// Reads a request, including its body if it has a length, so that
+/// the connection isn't reset while the client is still sending.
+fn read_request(stream: &mut TcpStream) -> String {
+    let mut request = Vec::new();
+    let mut buf = [0; 1024];
+    loop {
+        let n = stream.read(&mut buf).unwrap();
+        request.extend_from_slice(&buf[..n]);
+        let text = String::from_utf8_lossy(&request);
+        let complete = text.split_once("\r\n\r\n").is_some_and(|(head, body)| {
+            let len = head
+                .lines()
+                .find_map(|line| {
+                    let (name, value) = line.split_once(':')?;
+                    name.eq_ignore_ascii_case("content-length")
+                        .then(|| value.trim().parse::<usize>().ok())?
+                })
+                .unwrap_or(0);
+            body.len() >= len
+        });
+        if complete || n == 0 {
+            break;
+        }
+    }
+    String::from_utf8_lossy(&request).to_string()
+}
@@ -1,10 +1,19 @@
+mod archive;
+mod cache;
 pub mod cli;
 mod config;
 mod error;
+mod gitea;
 pub mod github;
+mod gitlab;
 pub mod handler;
+mod http;
 pub mod pkg;
 pub mod pkgs;
+mod progress;
+mod registry;
+mod source;
+mod url_source;
 
 #[allow(unused)]
 mod util;
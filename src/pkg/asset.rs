@@ -1,8 +1,10 @@
-use super::{AssetCallback, AssetFetcher, Dirs, Installer, PkgInfo, Release};
-use crate::{error::Error, util};
+use super::checksum::{self, Checksum};
+use super::{Asset, AssetCallback, AssetFetcher, Dirs, Installer, PkgInfo, Release, Signing};
+use crate::error::Error;
 use anyhow::Result;
 use regex::Regex;
 use std::fs;
+use std::path::Path;
 
 /// Used by packages installing from a github release assset.
 pub struct GithubReleaseInstaller {
@@ -25,6 +27,71 @@ impl GithubReleaseInstaller {
     }
 }
 
+impl GithubReleaseInstaller {
+    /// Gives the checksum of the asset published with the
+    /// release, e.g. in a checksums.txt file, if any.
+    fn published_checksum(
+        &self,
+        release: &Release,
+        asset: &Asset,
+        target_dir: &Path,
+    ) -> Result<Option<Checksum>, Error> {
+        let file = match checksum::find_checksum_asset(release, asset) {
+            Some(file) => file,
+            None => return Ok(None),
+        };
+
+        log::info!("Found checksums of {} in {}", asset.name, file.name);
+        let path = target_dir.join(&file.name);
+        self.assets.download(file, &path)?;
+        let content = fs::read_to_string(&path);
+        fs::remove_file(&path)?;
+
+        let checksum = checksum::parse_checksums(&content?, &asset.name, &file.name);
+        if checksum.is_none() {
+            log::warn!("No checksum for {} in {}", asset.name, file.name);
+        }
+        Ok(checksum)
+    }
+
+    /// Verifies the signature of the downloaded asset at `path`,
+    /// published with the release alongside it.
+    fn verify_signature(
+        &self,
+        signing: &Signing,
+        release: &Release,
+        asset: &Asset,
+        path: &Path,
+    ) -> Result<(), Error> {
+        let error = |reason: String| Error::Signature {
+            asset: asset.name.to_owned(),
+            reason,
+        };
+
+        let file = signing
+            .find_signature(release, asset)
+            .ok_or_else(|| error(format!("no {} signature published", signing.scheme())))?;
+
+        let sig_path = path.with_file_name(&file.name);
+        let sha256 = self.assets.download(file, &sig_path)?;
+        // A signature from a lockfile must be the locked one.
+        let result = match &file.sha256 {
+            Some(expected) => Checksum::sha256(expected).verify(&file.name, &sig_path, &sha256),
+            None => Ok(()),
+        };
+        let result = result.and_then(|_| {
+            signing
+                .verify(path, &sig_path)
+                .map_err(|err| error(format!("{:#}", err)))
+        });
+        fs::remove_file(&sig_path)?;
+
+        result?;
+        log::info!("Verified {} signature of {}", signing.scheme(), asset.name);
+        Ok(())
+    }
+}
+
 unsafe impl Send for GithubReleaseInstaller {}
 unsafe impl Sync for GithubReleaseInstaller {}
 
@@ -33,7 +100,12 @@ impl Installer for GithubReleaseInstaller {
         "GitHub releases"
     }
 
-    fn install(&self, info: &PkgInfo, dirs: &Dirs, release: Option<&Release>) -> Result<(), Error> {
+    fn install(
+        &self,
+        info: &PkgInfo,
+        dirs: &Dirs,
+        release: Option<&Release>,
+    ) -> Result<Option<Checksum>, Error> {
         if release.is_none() {
             return Err(Error::MissingRelease);
         }
@@ -57,9 +129,38 @@ impl Installer for GithubReleaseInstaller {
             }
         };
 
-        let bytes = self.assets.download(asset)?;
+        // A checksum from a lockfile takes precedence over a published one.
+        let expected = match &asset.sha256 {
+            Some(sha256) => Some(Checksum::sha256(sha256)),
+            None => self.published_checksum(release, asset, &target_dir)?,
+        };
+        if expected.is_none() && info.require_checksum {
+            return Err(Error::MissingChecksum(asset.name.to_owned()));
+        }
+
         let targz = target_dir.join(&asset.name);
-        util::write_file(&targz, &bytes)?;
+        let sha256 = self.assets.download(asset, &targz)?;
+        let checksum = match expected {
+            Some(expected) => {
+                if let Err(err) = expected.verify(&asset.name, &targz, &sha256) {
+                    let _ = fs::remove_file(&targz);
+                    return Err(err);
+                }
+                log::info!("Verified {} of {}", expected, asset.name);
+                expected
+            }
+            None => {
+                log::warn!("No checksum published for {}", asset.name);
+                Checksum::sha256(&sha256)
+            }
+        };
+
+        if let Some(signing) = &info.signing {
+            if let Err(err) = self.verify_signature(signing, release, asset, &targz) {
+                let _ = fs::remove_file(&targz);
+                return Err(err);
+            }
+        }
 
         log::info!("Wrote tar.gz file to {:?}", &targz);
 
@@ -70,7 +171,98 @@ impl Installer for GithubReleaseInstaller {
                 reason: format!("{}", err),
             })
         } else {
-            Ok(())
+            Ok(Some(checksum))
+        }
+    }
+
+    fn find_asset<'a>(&self, release: &'a Release) -> Option<&'a Asset> {
+        let regex = Regex::new(&self.pattern).ok()?;
+        release
+            .assets
+            .iter()
+            .find(|asset| regex.is_match(&asset.name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pkg::{LockedAsset, LockedPackage, LockedSignature};
+    use crate::util;
+    use std::collections::HashMap;
+
+    const SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RWQf6LRCGA9i59SLOFxz6NxvASXDJeRtuZykwQepbDEGt87ig1BNpWaVWuNrm73YiIiJbq71Wi+dP9eKL8OC351vwIasSSbXxwA=
+trusted comment: timestamp:1555779966\tfile:test
+QtKMXWyYcwdpZAlPF7tE2ENJkRd1ujvKjlj1m9RtHTBnZPa5WKU5uWRs5GoP5M/VqE81QFuMKI5k/SfNQUaOAA==
+";
+
+    /// Serves assets by URL from memory.
+    struct Assets(HashMap<String, Vec<u8>>);
+
+    impl AssetFetcher for Assets {
+        fn download(&self, asset: &Asset, dest: &Path) -> Result<String> {
+            let bytes = &self.0[&asset.url];
+            fs::write(dest, bytes)?;
+            Ok(util::sha256_hex(bytes))
         }
     }
+
+    fn locked(signature: &str) -> LockedPackage {
+        let mut pkg = LockedPackage::new("tool".into(), "v1.0.0".into());
+        pkg.assets.push(LockedAsset {
+            platform: "x86_64-linux-gnu".into(),
+            name: "tool".into(),
+            url: "https://example.com/tool".into(),
+            sha256: util::sha256_hex(b"test"),
+            signature: Some(LockedSignature {
+                name: "tool.minisig".into(),
+                url: "https://example.com/tool.minisig".into(),
+                sha256: util::sha256_hex(signature.as_bytes()),
+            }),
+        });
+        pkg
+    }
+
+    fn install(pkg: &LockedPackage, signature: &str) -> Result<(), Error> {
+        let dir = tempfile::tempdir_in(".")?;
+        let dirs = Dirs {
+            root_dir: dir.path().to_path_buf(),
+            bin_dir: dir.path().join("bin"),
+            pkg_dir: dir.path().join("pkg"),
+        };
+
+        let assets = Assets(HashMap::from([
+            ("https://example.com/tool".to_string(), b"test".to_vec()),
+            (
+                "https://example.com/tool.minisig".to_string(),
+                signature.as_bytes().to_vec(),
+            ),
+        ]));
+        let installer = GithubReleaseInstaller::new(
+            "tool".into(),
+            Box::new(assets),
+            Box::new(|_, _, _| Ok(())),
+        );
+
+        let mut info = crate::pkg_info!("owner/tool", "tool");
+        info.signing = Some(Signing::Minisign {
+            public_key: "RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3".into(),
+        });
+
+        let release = pkg.release("x86_64-linux-gnu")?;
+        installer.install(&info, &dirs, Some(&release)).map(|_| ())
+    }
+
+    #[test]
+    fn test_install_locked_signed() {
+        if let Err(err) = install(&locked(SIGNATURE), SIGNATURE) {
+            panic!("failed to install: {}", err);
+        }
+
+        // A signature other than the locked one is refused.
+        let other = SIGNATURE.replace("timestamp:1555779966", "timestamp:1555779967");
+        let err = install(&locked(SIGNATURE), &other).expect_err("unlocked signature");
+        assert!(matches!(err, Error::ChecksumMismatch { .. }));
+    }
 }
@@ -1,10 +1,22 @@
-use super::{Dirs, Installer, PkgInfo, Release, Version};
+use super::{Checksum, Dirs, Installer, PkgInfo, Release};
+use crate::config::CRATES_INDEX;
+use crate::registry::Registry;
 use crate::{error::Error, util};
 use anyhow::Result;
 use std::process;
 
 #[derive(Default)]
-pub struct CargoInstaller {}
+pub struct CargoInstaller {
+    /// Sparse index to install from instead of crates.io.
+    index: Option<String>,
+}
+
+impl CargoInstaller {
+    pub fn with_index(mut self, index: Option<String>) -> Self {
+        self.index = index;
+        self
+    }
+}
 
 unsafe impl Send for CargoInstaller {}
 unsafe impl Sync for CargoInstaller {}
@@ -14,30 +26,56 @@ impl Installer for CargoInstaller {
         "Cargo"
     }
 
-    fn install(&self, info: &PkgInfo, dirs: &Dirs, release: Option<&Release>) -> Result<(), Error> {
+    fn registry(&self) -> Option<Registry> {
+        let url = self.index.as_deref().unwrap_or(CRATES_INDEX);
+        Some(Registry::Crates(url.to_string()))
+    }
+
+    fn install(
+        &self,
+        info: &PkgInfo,
+        dirs: &Dirs,
+        release: Option<&Release>,
+    ) -> Result<Option<Checksum>, Error> {
         util::require_command("cargo")?;
 
         let mut cmd = process::Command::new("cargo");
         cmd.arg("install");
         cmd.arg("--root");
         cmd.arg(&dirs.root_dir);
+        if let Some(index) = &self.index {
+            cmd.arg("--index");
+            cmd.arg(format!("sparse+{}", index));
+        }
         if let Some(release) = release {
-            if let Ok(version) = release.try_get_version() {
-                if let Version::Sem(maj, min, pat) = &version {
-                    log::info!("Found semver version for {}: { }", &info.name, version);
+            let version = release.package_version().ok();
+            match version.as_deref().and_then(crate_version) {
+                Some(version) => {
+                    log::info!("Found crate version for {}: {}", &info.name, version);
                     cmd.arg("--version");
-                    cmd.arg(format!("{}.{}.{}", maj, min, pat));
-                } else {
-                    log::info!(
-                        "No semver found for {}, proceeding with latest version",
-                        &info.name
-                    );
+                    cmd.arg(version);
                 }
+                None => log::info!(
+                    "No semver found for {}, proceeding with latest version",
+                    &info.name
+                ),
             }
         }
         cmd.arg(&info.mod_name);
         util::run_cmd(&mut cmd)?;
 
-        Ok(())
+        Ok(None)
     }
 }
+
+/// Gives the version of a crate, e.g. `1.2.3`, `100.0.0` or
+/// `1.0.0-rc.1` of `v1.0.0-rc.1`, if the version looks like one.
+fn crate_version(version: &str) -> Option<&str> {
+    let version = version.trim_start_matches('v');
+    let core = version.split(['-', '+']).next().unwrap_or_default();
+    let parts: Vec<&str> = core.split('.').collect();
+    let numeric = parts
+        .iter()
+        .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()));
+    (parts.len() == 3 && numeric).then_some(version)
+}
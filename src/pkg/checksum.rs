@@ -0,0 +1,238 @@
+use super::{Asset, Release};
+use crate::error::Error;
+use sha2::{Digest, Sha512};
+use std::path::Path;
+use std::{fmt, fs, io};
+
+/// Suffixes of files with the checksum of a single asset,
+/// e.g. `nu-0.95.0-x86_64-unknown-linux-gnu.tar.gz.sha256`.
+const ASSET_SUFFIXES: [&str; 4] = [".sha256", ".sha256sum", ".sha512", ".sha512sum"];
+
+/// Names of files with the checksums of every asset, lowercased,
+/// e.g. `checksums.txt`, `SHA256SUMS` or `bat_0.24.0_checksums.txt`.
+const SHARED_NAMES: [&str; 3] = ["checksums", "sha256sums", "sha512sums"];
+
+/// Suffixes of signatures and certificates,
+/// which are never checksum files themselves.
+const SIGNATURE_SUFFIXES: [&str; 6] = [".sig", ".asc", ".pem", ".minisig", ".bundle", ".gpg"];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Algorithm {
+    Sha256,
+    Sha512,
+}
+
+/// An expected hash of an asset.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Checksum {
+    pub algorithm: Algorithm,
+    /// Lowercase hex encoded hash.
+    pub hex: String,
+}
+
+impl Checksum {
+    pub fn sha256(hex: &str) -> Self {
+        Self {
+            algorithm: Algorithm::Sha256,
+            hex: hex.to_lowercase(),
+        }
+    }
+
+    /// Parses a hex encoded hash, the algorithm given by its length.
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+
+        let algorithm = match hex.len() {
+            64 => Algorithm::Sha256,
+            128 => Algorithm::Sha512,
+            _ => return None,
+        };
+
+        Some(Self {
+            algorithm,
+            hex: hex.to_lowercase(),
+        })
+    }
+
+    /// Checks the downloaded asset at `path` against the checksum,
+    /// `sha256` being its already computed SHA-256.
+    pub fn verify(&self, asset: &str, path: &Path, sha256: &str) -> Result<(), Error> {
+        let actual = match self.algorithm {
+            Algorithm::Sha256 => sha256.to_lowercase(),
+            Algorithm::Sha512 => {
+                let mut hasher = Sha512::new();
+                io::copy(&mut fs::File::open(path)?, &mut hasher)?;
+                format!("{:x}", hasher.finalize())
+            }
+        };
+
+        if actual != self.hex {
+            return Err(Error::ChecksumMismatch {
+                asset: asset.to_owned(),
+                expected: self.to_string(),
+                actual: Checksum {
+                    algorithm: self.algorithm,
+                    hex: actual,
+                }
+                .to_string(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Checksum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.algorithm {
+            Algorithm::Sha256 => write!(f, "sha256:{}", self.hex),
+            Algorithm::Sha512 => write!(f, "sha512:{}", self.hex),
+        }
+    }
+}
+
+/// Finds the file among the release assets with the checksum
+/// of the asset, preferring one for the asset alone.
+pub fn find_checksum_asset<'a>(release: &'a Release, asset: &Asset) -> Option<&'a Asset> {
+    for suffix in ASSET_SUFFIXES {
+        let name = format!("{}{}", asset.name, suffix);
+        if let Some(found) = release.assets.iter().find(|a| a.name == name) {
+            return Some(found);
+        }
+    }
+
+    release.assets.iter().find(|a| {
+        let name = a.name.to_lowercase();
+        SHARED_NAMES.iter().any(|n| name.contains(n))
+            && !SIGNATURE_SUFFIXES.iter().any(|s| name.ends_with(s))
+            && !ASSET_SUFFIXES.iter().any(|s| name.ends_with(s))
+    })
+}
+
+/// Finds the checksum of the asset in the content of the checksum file.
+///
+/// Supports the output of e.g. sha256sum (`<hash>  <name>`, where the name
+/// may be prefixed by `*` for binary mode), the BSD style
+/// (`SHA256 (<name>) = <hash>`) and files of the asset with just a hash.
+pub fn parse_checksums(content: &str, asset: &str, file: &str) -> Option<Checksum> {
+    let lines: Vec<&str> = content
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect();
+
+    for line in &lines {
+        // BSD style
+        if let Some((name, hash)) = line.split_once(") = ") {
+            let name = name.split_once(" (").map(|(_, name)| name).unwrap_or(name);
+            if file_name(name) == asset {
+                return Checksum::from_hex(hash.trim());
+            }
+            continue;
+        }
+
+        let mut parts = line.split_whitespace();
+        let (hash, name) = match (parts.next(), parts.next()) {
+            (Some(hash), Some(name)) => (hash, name),
+            _ => continue,
+        };
+        if file_name(name.trim_start_matches('*')) == asset {
+            return Checksum::from_hex(hash);
+        }
+    }
+
+    // A file with the checksum of the asset alone may only have the hash.
+    match lines.as_slice() {
+        [line] if file.starts_with(asset) && !line.contains(char::is_whitespace) => {
+            Checksum::from_hex(line)
+        }
+        _ => None,
+    }
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHA256: &str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+    fn asset(name: &str) -> Asset {
+        Asset {
+            name: name.to_string(),
+            url: format!("https://github.com/a/b/releases/download/v1.0.0/{}", name),
+            sha256: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_checksums() {
+        let content = format!(
+            "# checksums\n{}  other.tar.gz\n{} *./bin/tool.tar.gz\n",
+            "0".repeat(64),
+            SHA256
+        );
+        assert_eq!(
+            parse_checksums(&content, "tool.tar.gz", "checksums.txt"),
+            Some(Checksum::sha256(SHA256))
+        );
+        assert!(parse_checksums(&content, "missing.tar.gz", "checksums.txt").is_none());
+
+        let bsd = format!("SHA512 (tool.tar.gz) = {}", "a".repeat(128));
+        let checksum = parse_checksums(&bsd, "tool.tar.gz", "SHA512SUMS").expect("checksum");
+        assert_eq!(checksum.algorithm, Algorithm::Sha512);
+
+        let single = format!("{}\n", SHA256.to_uppercase());
+        assert_eq!(
+            parse_checksums(&single, "tool.tar.gz", "tool.tar.gz.sha256"),
+            Some(Checksum::sha256(SHA256))
+        );
+        assert!(parse_checksums(&single, "tool.tar.gz", "checksums.txt").is_none());
+    }
+
+    #[test]
+    fn test_find_checksum_asset() {
+        let release = Release {
+            name: "v1.0.0".into(),
+            tag: "v1.0.0".into(),
+            prerelease: false,
+            assets: vec![
+                asset("tool.tar.gz"),
+                asset("tool_1.0.0_checksums.txt.sig"),
+                asset("tool_1.0.0_checksums.txt"),
+                asset("tool.tar.gz.sha256"),
+            ],
+        };
+
+        let found = find_checksum_asset(&release, &asset("tool.tar.gz"));
+        assert_eq!(found.map(|a| a.name.as_str()), Some("tool.tar.gz.sha256"));
+
+        let found = find_checksum_asset(&release, &asset("tool.zip"));
+        assert_eq!(
+            found.map(|a| a.name.as_str()),
+            Some("tool_1.0.0_checksums.txt")
+        );
+    }
+
+    #[test]
+    fn test_verify() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir_in(".")?;
+        let path = dir.path().join("abc");
+        fs::write(&path, b"abc")?;
+
+        assert!(Checksum::sha256(SHA256)
+            .verify("abc", &path, SHA256)
+            .is_ok());
+        let other = Checksum::sha256(&"0".repeat(64));
+        assert!(matches!(
+            other.verify("abc", &path, SHA256),
+            Err(Error::ChecksumMismatch { .. })
+        ));
+        Ok(())
+    }
+}
@@ -1,9 +1,21 @@
-use super::{Dirs, Installer, PkgInfo, Release};
+use super::{Checksum, Dirs, Installer, PkgInfo, Release};
+use crate::config::GOPROXY;
+use crate::registry::Registry;
 use crate::{error::Error, util};
 use anyhow::Result;
 
 #[derive(Default)]
-pub struct GoInstaller {}
+pub struct GoInstaller {
+    /// Module proxy to install from instead of the default one.
+    proxy: Option<String>,
+}
+
+impl GoInstaller {
+    pub fn with_proxy(mut self, proxy: Option<String>) -> Self {
+        self.proxy = proxy;
+        self
+    }
+}
 
 unsafe impl Send for GoInstaller {}
 unsafe impl Sync for GoInstaller {}
@@ -13,21 +25,34 @@ impl Installer for GoInstaller {
         "Go"
     }
 
-    fn install(&self, info: &PkgInfo, dirs: &Dirs, release: Option<&Release>) -> Result<(), Error> {
+    fn registry(&self) -> Option<Registry> {
+        let url = self.proxy.as_deref().unwrap_or(GOPROXY);
+        Some(Registry::GoProxy(url.to_string()))
+    }
+
+    fn install(
+        &self,
+        info: &PkgInfo,
+        dirs: &Dirs,
+        release: Option<&Release>,
+    ) -> Result<Option<Checksum>, Error> {
         util::require_command("go")?;
 
         let version = match release {
-            Some(release) => release.try_get_version()?.to_string(),
+            Some(release) => release.package_version()?,
             None => "latest".to_string(),
         };
 
         let mut cmd = util::new_cmd("go");
         cmd.env("GOBIN", &dirs.bin_dir);
+        if let Some(proxy) = &self.proxy {
+            cmd.env("GOPROXY", proxy.trim_end_matches('/'));
+        }
         cmd.arg("install");
         cmd.arg(format!("{}@{}", info.mod_name, version));
         cmd.status()?;
 
         util::run_cmd(&mut cmd)?;
-        Ok(())
+        Ok(None)
     }
 }
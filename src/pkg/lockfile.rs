@@ -0,0 +1,203 @@
+use super::{Asset, Release};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// A lockfile pins each package to an exact release tag
+/// and, for packages installed from release assets, the
+/// asset to use on every platform it was locked on.
+#[derive(Default, Deserialize, Serialize)]
+pub struct Lockfile {
+    #[serde(default, rename = "package")]
+    pub packages: Vec<LockedPackage>,
+}
+
+impl Lockfile {
+    pub fn get(&self, name: &str) -> Option<&LockedPackage> {
+        self.packages.iter().find(|pkg| pkg.name == name)
+    }
+
+    /// Updates or inserts a locked package.
+    ///
+    /// Assets locked on other platforms are kept as long as the
+    /// tag is unchanged, so that running `lock` on each platform
+    /// builds up a single lockfile.
+    pub fn upsert(&mut self, mut pkg: LockedPackage) {
+        match self.packages.iter_mut().find(|p| p.name == pkg.name) {
+            Some(existing) => {
+                if existing.tag == pkg.tag {
+                    let other_platforms: Vec<LockedAsset> = existing
+                        .assets
+                        .drain(..)
+                        .filter(|a| !pkg.assets.iter().any(|b| b.platform == a.platform))
+                        .collect();
+                    pkg.assets.extend(other_platforms);
+                    pkg.assets.sort_by(|a, b| a.platform.cmp(&b.platform));
+                }
+                *existing = pkg;
+            }
+            None => {
+                self.packages.push(pkg);
+                self.packages.sort_by(|a, b| a.name.cmp(&b.name));
+            }
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct LockedPackage {
+    pub name: String,
+    /// The resolved release tag.
+    pub tag: String,
+    #[serde(default, rename = "asset", skip_serializing_if = "Vec::is_empty")]
+    pub assets: Vec<LockedAsset>,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct LockedAsset {
+    /// The platform the asset was chosen for, e.g. x86_64-linux-gnu.
+    pub platform: String,
+    pub name: String,
+    pub url: String,
+    /// Hex encoded SHA-256 of the asset.
+    pub sha256: String,
+    /// The signature published with the asset,
+    /// for packages with signed release assets.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<LockedSignature>,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct LockedSignature {
+    pub name: String,
+    pub url: String,
+    /// Hex encoded SHA-256 of the signature file.
+    pub sha256: String,
+}
+
+impl LockedPackage {
+    pub fn new(name: String, tag: String) -> Self {
+        Self {
+            name,
+            tag,
+            assets: Vec::new(),
+        }
+    }
+
+    /// Builds the release to install on `platform`.
+    ///
+    /// The release only contains the locked asset and its
+    /// signature, carrying their checksums, so that nothing
+    /// but the locked bytes can be installed. A platform without a locked asset
+    /// gets a release without assets, which falls back to the
+    /// native installer as it did when the platform was locked.
+    pub fn release(&self, platform: &str) -> Result<Release> {
+        let assets: Vec<Asset> = self
+            .assets
+            .iter()
+            .filter(|asset| asset.platform == platform)
+            .flat_map(|asset| {
+                let signature = asset.signature.as_ref().map(|sig| Asset {
+                    name: sig.name.clone(),
+                    url: sig.url.clone(),
+                    sha256: Some(sig.sha256.clone()),
+                });
+                let asset = Asset {
+                    name: asset.name.clone(),
+                    url: asset.url.clone(),
+                    sha256: Some(asset.sha256.clone()),
+                };
+                std::iter::once(asset).chain(signature)
+            })
+            .collect();
+
+        Ok(Release {
+            name: self.tag.clone(),
+            tag: self.tag.clone(),
+            prerelease: false,
+            assets,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn locked(tag: &str, platform: &str) -> LockedPackage {
+        let mut pkg = LockedPackage::new("bat".to_string(), tag.to_string());
+        pkg.assets.push(LockedAsset {
+            platform: platform.to_string(),
+            name: format!("bat-{}.tar.gz", platform),
+            url: format!("https://example.com/bat-{}.tar.gz", platform),
+            sha256: "abc".to_string(),
+            signature: None,
+        });
+        pkg
+    }
+
+    #[test]
+    fn test_upsert_merges_platforms() {
+        let mut lockfile = Lockfile::default();
+        lockfile.upsert(locked("v0.24.0", "x86_64-linux-gnu"));
+        lockfile.upsert(locked("v0.24.0", "aarch64-macos"));
+
+        let pkg = lockfile.get("bat").unwrap();
+        assert_eq!(pkg.assets.len(), 2);
+    }
+
+    #[test]
+    fn test_upsert_new_tag_drops_platforms() {
+        let mut lockfile = Lockfile::default();
+        lockfile.upsert(locked("v0.24.0", "x86_64-linux-gnu"));
+        lockfile.upsert(locked("v0.25.0", "aarch64-macos"));
+
+        let pkg = lockfile.get("bat").unwrap();
+        assert_eq!(pkg.tag, "v0.25.0");
+        assert_eq!(pkg.assets.len(), 1);
+    }
+
+    #[test]
+    fn test_release() -> Result<()> {
+        let pkg = locked("v0.24.0", "x86_64-linux-gnu");
+        let release = pkg.release("x86_64-linux-gnu")?;
+        assert_eq!(release.assets.len(), 1);
+        assert_eq!(release.assets[0].sha256.as_deref(), Some("abc"));
+
+        let release = pkg.release("aarch64-macos")?;
+        assert!(release.assets.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_release_signed() -> Result<()> {
+        let mut pkg = locked("v0.24.0", "x86_64-linux-gnu");
+        pkg.assets[0].signature = Some(LockedSignature {
+            name: "bat-x86_64-linux-gnu.tar.gz.minisig".to_string(),
+            url: "https://example.com/bat-x86_64-linux-gnu.tar.gz.minisig".to_string(),
+            sha256: "def".to_string(),
+        });
+
+        let s = toml::to_string_pretty(&Lockfile {
+            packages: vec![pkg],
+        })?;
+        let lockfile: Lockfile = toml::from_str(&s)?;
+        let release = lockfile.get("bat").unwrap().release("x86_64-linux-gnu")?;
+        assert_eq!(release.assets.len(), 2);
+        assert_eq!(release.assets[0].name, "bat-x86_64-linux-gnu.tar.gz");
+        assert_eq!(release.assets[1].sha256.as_deref(), Some("def"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_roundtrip() -> Result<()> {
+        let mut lockfile = Lockfile::default();
+        lockfile.upsert(locked("v0.24.0", "x86_64-linux-gnu"));
+        lockfile.upsert(LockedPackage::new("gopls".into(), "v0.16.1".into()));
+
+        let s = toml::to_string_pretty(&lockfile)?;
+        let parsed: Lockfile = toml::from_str(&s)?;
+        assert_eq!(parsed.packages.len(), 2);
+        assert!(parsed.get("gopls").unwrap().assets.is_empty());
+        Ok(())
+    }
+}
@@ -1,16 +1,74 @@
 use super::Version;
 use serde::{Deserialize, Serialize};
 
+/// How the installed version of a package was chosen.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Channel {
+    /// The latest release at the time of installation.
+    #[default]
+    Latest,
+    /// A version that was explicitly requested.
+    Pinned,
+}
+
+/// An executable in the bin directory that belongs to a package.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Binary {
+    pub name: String,
+    /// Hex encoded SHA-256 of the file when it was installed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
 // TODO: give better name
 #[derive(Deserialize, Serialize)]
 pub struct Entry {
     pub name: String,
     pub version: Version,
+    #[serde(default)]
+    pub channel: Channel,
+    /// Name of the installer used, e.g. npm.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub installer: Option<String>,
+    /// Binaries the package added to the bin directory.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub binaries: Vec<Binary>,
+    /// Checksum of the installed release asset, e.g. sha256:<hex>.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<String>,
 }
 
 impl Entry {
     pub fn new(name: String, version: Version) -> Self {
-        Self { name, version }
+        Self {
+            name,
+            version,
+            channel: Channel::default(),
+            installer: None,
+            binaries: Vec::new(),
+            checksum: None,
+        }
+    }
+
+    pub fn with_channel(mut self, channel: Channel) -> Self {
+        self.channel = channel;
+        self
+    }
+
+    pub fn with_installer(mut self, installer: String) -> Self {
+        self.installer = Some(installer);
+        self
+    }
+
+    pub fn with_binaries(mut self, binaries: Vec<Binary>) -> Self {
+        self.binaries = binaries;
+        self
+    }
+
+    pub fn with_checksum(mut self, checksum: Option<String>) -> Self {
+        self.checksum = checksum;
+        self
     }
 }
 
@@ -5,26 +5,40 @@ use std::path::{Path, PathBuf};
 
 mod asset;
 mod cargo;
+mod checksum;
 mod golang;
+mod lockfile;
 mod manifest;
 mod npm;
 mod pip;
+mod signature;
+mod toolset;
 pub mod version;
 
 pub use asset::GithubReleaseInstaller;
 pub use cargo::CargoInstaller;
+pub use checksum::Checksum;
 pub use golang::GoInstaller;
-pub use manifest::{Entry, Manifest};
+pub use lockfile::{LockedAsset, LockedPackage, LockedSignature, Lockfile};
+pub use manifest::{Binary, Channel, Entry, Manifest};
 pub use npm::NpmInstaller;
 pub use pip::PipInstaller;
+pub use signature::Signing;
+pub use toolset::{Toolset, ToolsetEntry};
 pub use version::Version;
 
 use crate::error::Error;
+use crate::registry::Registry;
+use crate::url_source::UrlSource;
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Asset {
     pub name: String,
     pub url: String,
+    /// Expected SHA-256 of the asset, if known.
+    /// The asset is rejected when its content doesn't match.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
 }
 
 /// Represents a release.
@@ -41,6 +55,17 @@ impl Release {
         let version = Version::try_from(&self.tag)?;
         Ok(version)
     }
+
+    /// Gives the version to install with a package manager: the tag as is
+    /// if it's a version, like those resolved from registries, e.g.
+    /// `2024.8.6` on PyPI, otherwise the version found in the tag.
+    pub fn package_version(&self) -> Result<String> {
+        let tag = self.tag.strip_prefix('v').unwrap_or(&self.tag);
+        if tag.starts_with(|c: char| c.is_ascii_digit()) {
+            return Ok(self.tag.clone());
+        }
+        Ok(self.try_get_version()?.to_string())
+    }
 }
 
 pub struct Dirs {
@@ -63,6 +88,20 @@ pub struct PkgInfo {
     pub mod_name: String,
     /// Name of the binary, e.g gopls.
     pub bin_name: String,
+    /// Refuse to install release assets without a published checksum.
+    pub require_checksum: bool,
+    /// How release assets are signed, to verify them before extraction.
+    pub signing: Option<Signing>,
+    /// Name of the release asset for this system, with `{tag}` and
+    /// `{version}` (the tag without a `v` prefix) placeholders. Used to
+    /// install releases resolved without the API, which lists assets.
+    pub asset_template: Option<String>,
+    /// Where to resolve releases from instead of the forge of
+    /// the repo, for packages published on a download site.
+    pub url_source: Option<UrlSource>,
+    /// API URL of the Gitea or Forgejo instance the repo is on, e.g.
+    /// `https://git.example.com/api/v1`, for instances not configured as a host.
+    pub gitea: Option<String>,
 }
 
 /// pkg_args provides a more convenient way to
@@ -75,6 +114,11 @@ macro_rules! pkg_info {
             name: $name.to_string(),
             bin_name: $name.to_string(),
             mod_name: $name.to_string(),
+            require_checksum: false,
+            signing: None,
+            asset_template: None,
+            url_source: None,
+            gitea: None,
         }
     };
     ($repo:expr, $name:expr, $mod:expr) => {
@@ -83,6 +127,11 @@ macro_rules! pkg_info {
             name: $name.to_string(),
             bin_name: $name.to_string(),
             mod_name: $mod.to_string(),
+            require_checksum: false,
+            signing: None,
+            asset_template: None,
+            url_source: None,
+            gitea: None,
         }
     };
     ($repo:expr, $name:expr, $mod:expr, $bin:expr) => {
@@ -91,13 +140,19 @@ macro_rules! pkg_info {
             name: $name.to_string(),
             bin_name: $bin.to_string(),
             mod_name: $mod.to_string(),
+            require_checksum: false,
+            signing: None,
+            asset_template: None,
+            url_source: None,
+            gitea: None,
         }
     };
 }
 
 /// Trait for downloading assets for e.g Github releases.
 pub trait AssetFetcher {
-    fn download(&self, asset: &Asset) -> Result<Vec<u8>>;
+    /// Downloads the asset to `dest`, giving its SHA-256.
+    fn download(&self, asset: &Asset, dest: &Path) -> Result<String>;
 }
 
 // Called after an asset has been downloaded.
@@ -109,8 +164,25 @@ pub trait Installer: Send + Sync {
     /// Returns the name of the installer.
     fn name(&self) -> &str;
 
-    /// Install the package.
-    fn install(&self, info: &PkgInfo, dirs: &Dirs, release: Option<&Release>) -> Result<(), Error>;
+    /// Install the package, giving the checksum of
+    /// the downloaded release asset, if any.
+    fn install(
+        &self,
+        info: &PkgInfo,
+        dirs: &Dirs,
+        release: Option<&Release>,
+    ) -> Result<Option<Checksum>, Error>;
+
+    /// Returns the release asset that would be installed on this system, if any.
+    fn find_asset<'a>(&self, _release: &'a Release) -> Option<&'a Asset> {
+        None
+    }
+
+    /// Returns the registry the package is installed from, if any,
+    /// whose versions are installed rather than the tags of the repo.
+    fn registry(&self) -> Option<Registry> {
+        None
+    }
 
     /// Uninstalls the package.
     fn uninstall(&self, info: &PkgInfo, dirs: &Dirs) -> Result<()> {
@@ -128,6 +200,15 @@ pub trait Installer: Send + Sync {
     }
 }
 
+/// The outcome of successfully installing a package.
+pub struct Installation {
+    pub version: Version,
+    /// Name of the installer that installed the package.
+    pub installer: String,
+    /// Checksum of the installed release asset, if any.
+    pub checksum: Option<Checksum>,
+}
+
 /// This signifies if a package was installed or removed.
 pub enum CallbackOperation {
     Install,
@@ -174,6 +255,27 @@ impl Package {
         }
     }
 
+    /// Sets the name of the release asset for this system,
+    /// e.g. `bat-{tag}-x86_64-unknown-linux-gnu.tar.gz`.
+    pub fn with_asset_template(mut self, template: &str) -> Self {
+        self.info.asset_template = Some(template.to_string());
+        self
+    }
+
+    /// Resolves the releases of the package from a version index
+    /// and download URL template, rather than its repo.
+    pub fn with_url_source(mut self, source: UrlSource) -> Self {
+        self.info.url_source = Some(source);
+        self
+    }
+
+    /// Resolves the releases of the package from the Gitea or Forgejo
+    /// instance with the API URL, e.g. `https://git.example.com/api/v1`.
+    pub fn with_gitea(mut self, api_url: &str) -> Self {
+        self.info.gitea = Some(api_url.to_string());
+        self
+    }
+
     /// Gives the name of the package.
     pub fn name(&self) -> &String {
         &self.info.name
@@ -184,28 +286,119 @@ impl Package {
         &self.info.repo
     }
 
-    pub fn install(&self, release: Option<Release>, dirs: &Dirs) -> Result<Version> {
+    /// Installs the package from another repo, e.g. a
+    /// mirror on a GitHub Enterprise instance.
+    pub fn set_repo(&mut self, repo: &str) {
+        self.info.repo = repo.to_string();
+    }
+
+    /// Refuses to install release assets without a published checksum.
+    pub fn set_require_checksum(&mut self, require: bool) {
+        self.info.require_checksum = require;
+    }
+
+    /// Sets how release assets are signed, or
+    /// with `None` skips verifying their signatures.
+    pub fn set_signing(&mut self, signing: Option<Signing>) {
+        self.info.signing = signing;
+    }
+
+    /// Checks if release assets are only installed with a published
+    /// checksum or signature to verify them against.
+    pub fn verifies_assets(&self) -> bool {
+        self.info.require_checksum || self.info.signing.is_some()
+    }
+
+    /// Gives how the release assets of the package are signed, if they are.
+    pub fn signing(&self) -> Option<&Signing> {
+        self.info.signing.as_ref()
+    }
+
+    /// Overrides the name of the release asset for this system.
+    pub fn set_asset_template(&mut self, template: &str) {
+        self.info.asset_template = Some(template.to_string());
+    }
+
+    /// Gives the name of the release asset for this system, if known.
+    pub fn asset_template(&self) -> Option<&str> {
+        self.info.asset_template.as_deref()
+    }
+
+    /// Overrides where the releases of the package are resolved from.
+    pub fn set_url_source(&mut self, source: UrlSource) {
+        self.info.url_source = Some(source);
+    }
+
+    /// Gives the version index and download URL template of the package, if any.
+    pub fn url_source(&self) -> Option<&UrlSource> {
+        self.info.url_source.as_ref()
+    }
+
+    /// Overrides the API URL of the Gitea or Forgejo instance the repo is on.
+    pub fn set_gitea(&mut self, api_url: &str) {
+        self.info.gitea = Some(api_url.to_string());
+    }
+
+    /// Gives the API URL of the Gitea or Forgejo instance the repo is on,
+    /// if given for the package rather than configured as a host.
+    pub fn gitea(&self) -> Option<&str> {
+        self.info.gitea.as_deref()
+    }
+
+    /// Gives the registry the versions of the package are resolved from,
+    /// for packages installed by a package manager only, e.g. npm.
+    pub fn registry(&self) -> Option<Registry> {
+        match &self.asset_installer {
+            Some(_) => None,
+            None => self
+                .native_installer
+                .as_ref()
+                .and_then(|installer| installer.registry()),
+        }
+    }
+
+    /// Gives the name of the module, e.g. the npm package.
+    pub fn mod_name(&self) -> &String {
+        &self.info.mod_name
+    }
+
+    /// Gives the name of the main binary of the package.
+    pub fn bin_name(&self) -> &String {
+        &self.info.bin_name
+    }
+
+    /// Gives the release asset that would be installed on this system, if any.
+    pub fn find_asset<'a>(&self, release: &'a Release) -> Option<&'a Asset> {
+        self.asset_installer
+            .as_ref()
+            .and_then(|installer| installer.find_asset(release))
+    }
+
+    pub fn install(&self, release: Option<Release>, dirs: &Dirs) -> Result<Installation> {
         let version = match &release {
-            Some(r) => r.try_get_version(),
-            None => Ok(Version::Unknown("unknown".to_string())),
+            Some(r) => r.try_get_version()?,
+            None => Version::Unknown("unknown".to_string()),
         };
 
         if let Some(installer) = &self.asset_installer {
             log::info!("Trying to install {} from release asset", self.info.name);
 
-            if let Err(err) = installer.install(&self.info, dirs, release.as_ref()) {
-                match &err {
-                    Error::MissingSystemAsset => {
-                        log::info!("No asset found for system, checking if");
-                    }
-                    err => bail!("{}", err),
+            match installer.install(&self.info, dirs, release.as_ref()) {
+                Err(Error::MissingSystemAsset) => {
+                    log::info!("No asset found for system, checking if");
+                }
+                Err(err) => bail!("{}", err),
+                Ok(checksum) => {
+                    log::info!(
+                        "Succesfully installed {} from release asset",
+                        self.info.name
+                    );
+                    return Ok(Installation {
+                        version,
+                        installer: installer.name().to_string(),
+                        checksum,
+                    });
                 }
-            } else {
-                log::info!(
-                    "Succesfully installed {} from release asset",
-                    self.info.name
-                );
-                return version;
             }
         }
 
@@ -213,20 +406,23 @@ impl Package {
             println!("No release asset available for your system.");
             println!("Trying to install using {}", installer.name());
 
-            if let Err(err) = installer.install(&self.info, dirs, release.as_ref()) {
-                match &err {
-                    Error::MissingProg(prog) => {
-                        println!("Missing package manager for {}, {}", self.info.name, prog);
-                    }
-                    err => bail!("{}", err),
+            match installer.install(&self.info, dirs, release.as_ref()) {
+                Err(Error::MissingProg(prog)) => {
+                    println!("Missing package manager for {}, {}", self.info.name, prog);
+                }
+                Err(err) => bail!("{}", err),
+                Ok(checksum) => {
+                    log::info!(
+                        "Succesfully installed {} using {}",
+                        self.info.name,
+                        installer.name()
+                    );
+                    return Ok(Installation {
+                        version,
+                        installer: installer.name().to_string(),
+                        checksum,
+                    });
                 }
-            } else {
-                log::info!(
-                    "Succesfully installed {} using {}",
-                    self.info.name,
-                    installer.name()
-                );
-                return version;
             }
         }
 
@@ -236,7 +432,7 @@ impl Package {
         )
     }
 
-    pub fn update(&self, release: Option<Release>, dirs: &Dirs) -> Result<Version> {
+    pub fn update(&self, release: Option<Release>, dirs: &Dirs) -> Result<Installation> {
         self.uninstall(dirs)?;
         self.install(release, dirs)
     }
@@ -1,4 +1,6 @@
-use super::{CallbackOperation, Dirs, Installer, PackageCallback, PkgInfo, Release};
+use super::{CallbackOperation, Checksum, Dirs, Installer, PackageCallback, PkgInfo, Release};
+use crate::config::NPM_REGISTRY;
+use crate::registry::Registry;
 use crate::{error::Error, util};
 use anyhow::Result;
 use std::fs;
@@ -6,6 +8,8 @@ use std::fs;
 pub struct NpmInstaller {
     dependencies: Vec<String>,
     callback: Box<PackageCallback>,
+    /// Registry to install from instead of the default one.
+    registry: Option<String>,
 }
 
 impl NpmInstaller {
@@ -13,8 +17,14 @@ impl NpmInstaller {
         Self {
             dependencies,
             callback,
+            registry: None,
         }
     }
+
+    pub fn with_registry(mut self, registry: Option<String>) -> Self {
+        self.registry = registry;
+        self
+    }
 }
 
 unsafe impl Send for NpmInstaller {}
@@ -25,13 +35,22 @@ impl Installer for NpmInstaller {
         "npm"
     }
 
-    fn install(&self, info: &PkgInfo, dirs: &Dirs, release: Option<&Release>) -> Result<(), Error> {
+    fn registry(&self) -> Option<Registry> {
+        let url = self.registry.as_deref().unwrap_or(NPM_REGISTRY);
+        Some(Registry::Npm(url.to_string()))
+    }
+
+    fn install(
+        &self,
+        info: &PkgInfo,
+        dirs: &Dirs,
+        release: Option<&Release>,
+    ) -> Result<Option<Checksum>, Error> {
         util::require_command("npm")?;
 
         let name = match release {
             Some(r) => {
-                let version = r.try_get_version()?;
-                format!("{}@{}", info.mod_name, version)
+                format!("{}@{}", info.mod_name, r.package_version()?)
             }
             None => info.mod_name.to_string(),
         };
@@ -47,6 +66,10 @@ impl Installer for NpmInstaller {
         cmd.arg("--global");
         cmd.arg("--prefix");
         cmd.arg(&target_dir);
+        if let Some(registry) = &self.registry {
+            cmd.arg("--registry");
+            cmd.arg(registry);
+        }
         cmd.arg(name);
         cmd.args(&self.dependencies);
         util::run_cmd(&mut cmd)?;
@@ -60,7 +83,7 @@ impl Installer for NpmInstaller {
 
         self.callback.as_ref()(CallbackOperation::Install, info, dirs)?;
 
-        Ok(())
+        Ok(None)
     }
 
     fn uninstall(&self, info: &PkgInfo, dirs: &Dirs) -> Result<()> {
@@ -1,15 +1,27 @@
-use super::{Dirs, Installer, PkgInfo, Release};
+use super::{Checksum, Dirs, Installer, PkgInfo, Release};
+use crate::config::PYPI_INDEX;
+use crate::registry::{self, Registry};
 use crate::{error::Error, util};
 use anyhow::Result;
 use std::fs;
 
 pub struct PipInstaller {
     dependencies: Vec<String>,
+    /// Package index to install from instead of PyPI.
+    index_url: Option<String>,
 }
 
 impl PipInstaller {
     pub fn new(dependencies: Vec<String>) -> Self {
-        Self { dependencies }
+        Self {
+            dependencies,
+            index_url: None,
+        }
+    }
+
+    pub fn with_index_url(mut self, index_url: Option<String>) -> Self {
+        self.index_url = index_url;
+        self
     }
 }
 
@@ -21,13 +33,22 @@ impl Installer for PipInstaller {
         "pip"
     }
 
-    fn install(&self, info: &PkgInfo, dirs: &Dirs, release: Option<&Release>) -> Result<(), Error> {
+    fn registry(&self) -> Option<Registry> {
+        let index_url = self.index_url.as_deref().unwrap_or(PYPI_INDEX);
+        Some(Registry::PyPI(registry::pypi_json_url(index_url)))
+    }
+
+    fn install(
+        &self,
+        info: &PkgInfo,
+        dirs: &Dirs,
+        release: Option<&Release>,
+    ) -> Result<Option<Checksum>, Error> {
         util::require_command("python")?;
 
         let name = match release {
             Some(r) => {
-                let version = r.try_get_version()?;
-                format!("{}=={}", info.mod_name, version)
+                format!("{}=={}", info.mod_name, r.package_version()?)
             }
             None => info.mod_name.to_string(),
         };
@@ -50,7 +71,11 @@ impl Installer for PipInstaller {
         // Install into virtual env
         let pip_path = venv_dir.join("bin").join("pip");
         let mut cmd = util::new_cmd(pip_path);
-        cmd.args(["install", "--upgrade", &name]);
+        cmd.args(["install", "--upgrade"]);
+        if let Some(index_url) = &self.index_url {
+            cmd.args(["--index-url", index_url]);
+        }
+        cmd.arg(&name);
         cmd.args(&self.dependencies);
         util::run_cmd(&mut cmd)?;
 
@@ -59,6 +84,6 @@ impl Installer for PipInstaller {
         let original = venv_dir.join("bin").join(&info.bin_name);
         util::symlink(&original, &link)?;
 
-        Ok(())
+        Ok(None)
     }
 }
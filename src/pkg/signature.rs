@@ -0,0 +1,296 @@
+use super::{Asset, Release};
+use crate::util;
+use anyhow::{anyhow, bail, Context, Result};
+use base64::prelude::{Engine, BASE64_STANDARD};
+use p256::ecdsa::signature::Verifier;
+use p256::ecdsa::{Signature, VerifyingKey};
+use p256::pkcs8::DecodePublicKey;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+/// How the release assets of a package are signed,
+/// with the public key to verify the signatures with.
+///
+/// The public key is either given inline
+/// or as the path of a file containing it.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "scheme", rename_all = "lowercase")]
+pub enum Signing {
+    /// Signatures made with minisign, in `<asset>.minisig`,
+    /// e.g. `public-key = "RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3"`.
+    Minisign {
+        #[serde(rename = "public-key")]
+        public_key: String,
+    },
+    /// Bundles written by `cosign sign-blob --bundle`, in `<asset>.bundle`
+    /// or `<asset>.sigstore.json`. Verified offline against a pinned
+    /// ECDSA P-256 key in PEM, without consulting the transparency log.
+    Cosign {
+        #[serde(rename = "public-key")]
+        public_key: String,
+    },
+    /// Detached GPG signatures, in `<asset>.sig` or `<asset>.asc`,
+    /// verified using gpg against an armored public key.
+    Gpg {
+        #[serde(rename = "public-key")]
+        public_key: String,
+    },
+}
+
+impl Signing {
+    pub fn scheme(&self) -> &'static str {
+        match self {
+            Signing::Minisign { .. } => "minisign",
+            Signing::Cosign { .. } => "cosign",
+            Signing::Gpg { .. } => "GPG",
+        }
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        match self {
+            Signing::Minisign { .. } => &[".minisig"],
+            Signing::Cosign { .. } => &[".bundle", ".sigstore.json", ".sigstore"],
+            Signing::Gpg { .. } => &[".sig", ".asc", ".gpg"],
+        }
+    }
+
+    /// Finds the signature of the asset among the release assets.
+    pub fn find_signature<'a>(&self, release: &'a Release, asset: &Asset) -> Option<&'a Asset> {
+        self.suffixes().iter().find_map(|suffix| {
+            let name = format!("{}{}", asset.name, suffix);
+            release.assets.iter().find(|a| a.name == name)
+        })
+    }
+
+    /// Verifies the signature (at `signature`) of the file at `path`.
+    pub fn verify(&self, path: &Path, signature: &Path) -> Result<()> {
+        match self {
+            Signing::Minisign { public_key } => verify_minisign(public_key, path, signature),
+            Signing::Cosign { public_key } => verify_cosign(public_key, path, signature),
+            Signing::Gpg { public_key } => verify_gpg(public_key, path, signature),
+        }
+    }
+}
+
+/// Gives the key given inline, or read from the file it names.
+fn load_key(key: &str) -> Result<String> {
+    let key = key.trim();
+    if key.starts_with("-----BEGIN") || !Path::new(key).exists() {
+        return Ok(key.to_string());
+    }
+
+    fs::read_to_string(key).with_context(|| format!("failed to read public key {}", key))
+}
+
+fn verify_minisign(public_key: &str, path: &Path, signature: &Path) -> Result<()> {
+    let key = load_key(public_key)?;
+    // Both the bare key and the content of a .pub file are accepted.
+    let key = match key.lines().last() {
+        Some(line) => minisign_verify::PublicKey::from_base64(line.trim()),
+        None => bail!("empty minisign public key"),
+    }
+    .map_err(|err| anyhow!("invalid minisign public key: {}", err))?;
+
+    let signature = minisign_verify::Signature::from_file(signature)
+        .map_err(|err| anyhow!("invalid minisign signature: {}", err))?;
+
+    match key.verify_stream(&signature) {
+        Ok(mut verifier) => {
+            let mut file = fs::File::open(path)?;
+            let mut buf = vec![0; 64 * 1024];
+            loop {
+                let n = file.read(&mut buf)?;
+                if n == 0 {
+                    break;
+                }
+                verifier.update(&buf[..n]);
+            }
+            verifier.finalize()
+        }
+        // Signatures of older versions of minisign sign the file itself.
+        Err(minisign_verify::Error::UnsupportedLegacyMode) => {
+            key.verify(&fs::read(path)?, &signature, true)
+        }
+        Err(err) => Err(err),
+    }
+    .map_err(|err| anyhow!("{}", err))
+}
+
+/// The signature in a cosign bundle, either the
+/// format of cosign 2 or a sigstore bundle.
+#[derive(Deserialize)]
+struct CosignBundle {
+    #[serde(rename = "base64Signature")]
+    base64_signature: Option<String>,
+    #[serde(rename = "messageSignature")]
+    message_signature: Option<MessageSignature>,
+}
+
+#[derive(Deserialize)]
+struct MessageSignature {
+    #[serde(rename = "messageDigest")]
+    message_digest: Option<MessageDigest>,
+    signature: String,
+}
+
+#[derive(Deserialize)]
+struct MessageDigest {
+    algorithm: String,
+    digest: String,
+}
+
+fn verify_cosign(public_key: &str, path: &Path, signature: &Path) -> Result<()> {
+    let key = VerifyingKey::from_public_key_pem(&load_key(public_key)?)
+        .map_err(|err| anyhow!("invalid cosign public key: {}", err))?;
+
+    let bundle: CosignBundle = util::json_from_file(signature)?;
+    let encoded = match (bundle.base64_signature, bundle.message_signature) {
+        (Some(signature), _) => signature,
+        (None, Some(message)) => {
+            if let Some(digest) = message.message_digest {
+                let expected = BASE64_STANDARD.decode(digest.digest)?;
+                let actual = util::sha256_file(path)?;
+                if digest.algorithm != "SHA2_256" || hex(&expected) != actual {
+                    bail!("message digest in bundle doesn't match the asset");
+                }
+            }
+            message.signature
+        }
+        (None, None) => bail!("no signature in cosign bundle"),
+    };
+
+    let signature = Signature::from_der(&BASE64_STANDARD.decode(encoded)?)
+        .map_err(|err| anyhow!("invalid cosign signature: {}", err))?;
+    key.verify(&fs::read(path)?, &signature)
+        .map_err(|_| anyhow!("signature doesn't match"))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn verify_gpg(public_key: &str, path: &Path, signature: &Path) -> Result<()> {
+    util::require_command("gpg").map_err(|err| anyhow!("{}", err))?;
+
+    // Verify against a keyring of the given key alone,
+    // rather than every key trusted by the user.
+    let home = path.with_extension("gnupg");
+    fs::create_dir_all(&home)?;
+    let result = (|| {
+        let key_path = home.join("key.asc");
+        fs::write(&key_path, load_key(public_key)?)?;
+
+        let mut cmd = util::new_cmd("gpg");
+        cmd.arg("--homedir")
+            .arg(&home)
+            .arg("--batch")
+            .arg("--quiet");
+        cmd.arg("--import").arg(&key_path);
+        util::run_cmd(&mut cmd).context("failed to import GPG public key")?;
+
+        let mut cmd = util::new_cmd("gpg");
+        cmd.arg("--homedir")
+            .arg(&home)
+            .arg("--batch")
+            .arg("--quiet");
+        cmd.arg("--verify").arg(signature).arg(path);
+        util::run_cmd(&mut cmd).context("bad GPG signature")
+    })();
+
+    let _ = fs::remove_dir_all(&home);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use p256::ecdsa::signature::Signer;
+    use p256::ecdsa::SigningKey;
+    use p256::pkcs8::EncodePublicKey;
+
+    #[test]
+    fn test_minisign() -> Result<()> {
+        let dir = tempfile::tempdir_in(".")?;
+        let path = dir.path().join("test");
+        let sig = dir.path().join("test.minisig");
+        fs::write(&path, b"test")?;
+        fs::write(
+            &sig,
+            "untrusted comment: signature from minisign secret key
+RWQf6LRCGA9i59SLOFxz6NxvASXDJeRtuZykwQepbDEGt87ig1BNpWaVWuNrm73YiIiJbq71Wi+dP9eKL8OC351vwIasSSbXxwA=
+trusted comment: timestamp:1555779966\tfile:test
+QtKMXWyYcwdpZAlPF7tE2ENJkRd1ujvKjlj1m9RtHTBnZPa5WKU5uWRs5GoP5M/VqE81QFuMKI5k/SfNQUaOAA==
+",
+        )?;
+
+        let signing = Signing::Minisign {
+            public_key: "RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3".into(),
+        };
+        signing.verify(&path, &sig)?;
+
+        fs::write(&path, b"Test")?;
+        assert!(signing.verify(&path, &sig).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cosign() -> Result<()> {
+        let dir = tempfile::tempdir_in(".")?;
+        let path = dir.path().join("asset.tar.gz");
+        let bundle = dir.path().join("asset.tar.gz.bundle");
+        fs::write(&path, b"asset")?;
+
+        let key = SigningKey::from_slice(&[7; 32])?;
+        let pem = key
+            .verifying_key()
+            .to_public_key_pem(p256::pkcs8::LineEnding::LF)
+            .map_err(|err| anyhow!("{}", err))?;
+        let signature: Signature = key.sign(b"asset");
+        let encoded = BASE64_STANDARD.encode(signature.to_der().as_bytes());
+        fs::write(
+            &bundle,
+            format!(r#"{{"base64Signature": "{}", "cert": ""}}"#, encoded),
+        )?;
+
+        let signing = Signing::Cosign { public_key: pem };
+        signing.verify(&path, &bundle)?;
+
+        fs::write(&path, b"tampered")?;
+        assert!(signing.verify(&path, &bundle).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_signature() {
+        let asset = |name: &str| Asset {
+            name: name.to_string(),
+            url: format!("https://example.com/{}", name),
+            sha256: None,
+        };
+        let release = Release {
+            name: "v1.0.0".into(),
+            tag: "v1.0.0".into(),
+            prerelease: false,
+            assets: vec![
+                asset("tool.tar.gz"),
+                asset("tool.tar.gz.sig"),
+                asset("tool.tar.gz.minisig"),
+            ],
+        };
+
+        let minisign = Signing::Minisign {
+            public_key: String::new(),
+        };
+        let found = minisign.find_signature(&release, &asset("tool.tar.gz"));
+        assert_eq!(found.map(|a| a.name.as_str()), Some("tool.tar.gz.minisig"));
+
+        let cosign = Signing::Cosign {
+            public_key: String::new(),
+        };
+        assert!(cosign
+            .find_signature(&release, &asset("tool.tar.gz"))
+            .is_none());
+    }
+}
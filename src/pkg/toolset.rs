@@ -0,0 +1,75 @@
+use super::{Channel, Manifest, Version};
+use serde::{Deserialize, Serialize};
+
+/// A shareable description of a set of installed packages,
+/// written by `export` and read by `import`.
+#[derive(Default, Deserialize, Serialize)]
+pub struct Toolset {
+    #[serde(default, rename = "package")]
+    pub packages: Vec<ToolsetEntry>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ToolsetEntry {
+    pub name: String,
+    pub version: Version,
+    #[serde(default)]
+    pub channel: Channel,
+    /// Name of the installer used on the exporting machine.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub installer: Option<String>,
+}
+
+impl From<&Manifest> for Toolset {
+    fn from(manifest: &Manifest) -> Self {
+        let mut packages: Vec<ToolsetEntry> = manifest
+            .packages
+            .iter()
+            .map(|entry| ToolsetEntry {
+                name: entry.name.clone(),
+                version: entry.version.clone(),
+                channel: entry.channel,
+                installer: entry.installer.clone(),
+            })
+            .collect();
+
+        packages.sort_by(|a, b| a.name.cmp(&b.name));
+        Self { packages }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pkg::Entry;
+    use anyhow::Result;
+
+    #[test]
+    fn test_roundtrip() -> Result<()> {
+        let mut manifest = Manifest::default();
+        manifest.upsert(Entry::new("gopls".into(), Version::Sem(0, 16, 1)));
+        manifest.upsert(
+            Entry::new("bat".into(), Version::Sem(0, 24, 0))
+                .with_channel(Channel::Pinned)
+                .with_installer("GitHub releases".into()),
+        );
+
+        manifest.upsert(Entry::new(
+            "pylsp".into(),
+            Version::Unknown("2024.8.6".into()),
+        ));
+
+        let s = toml::to_string_pretty(&Toolset::from(&manifest))?;
+        let toolset: Toolset = toml::from_str(&s)?;
+
+        assert_eq!(toolset.packages.len(), 3);
+        let pylsp = toolset.packages.iter().find(|p| p.name == "pylsp").unwrap();
+        assert_eq!(pylsp.version.to_string(), "2024.8.6");
+        let bat = &toolset.packages[0];
+        assert_eq!(bat.name, "bat");
+        assert_eq!(bat.version.to_string(), "v0.24.0");
+        assert_eq!(bat.channel, Channel::Pinned);
+        assert_eq!(bat.installer.as_deref(), Some("GitHub releases"));
+        Ok(())
+    }
+}
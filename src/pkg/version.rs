@@ -5,7 +5,8 @@ use serde::{de::Visitor, Deserialize, Serialize};
 use std::fmt;
 
 lazy_static! {
-    static ref SEMVER: Regex = Regex::new(r"(v?\d{1,2}\.\d{1,2}\.\d{1,2})$").unwrap();
+    // Not part of a larger number, e.g. CalVer `2024.08.06` or `100.0.0`.
+    static ref SEMVER: Regex = Regex::new(r"(?:^|\D)(v?\d{1,2}\.\d{1,2}\.\d{1,2})$").unwrap();
     static ref DATE: Regex = Regex::new(r"^(\d{4}-\d{2}-\d{2})").unwrap();
 }
 
@@ -72,6 +73,17 @@ impl fmt::Display for Version {
     }
 }
 
+/// Gives the tag of a version, normalized like the tags of
+/// forges, e.g. `v0.32.0` of `0.32.0`, for comparisons with
+/// installed versions. Versions that aren't semantic versions
+/// or dates, e.g. `2024.08.06`, are kept as is.
+pub fn normalize_tag(version: &str) -> String {
+    match Version::try_from(version) {
+        Ok(v @ (Version::Sem(..) | Version::Date(..))) => v.to_string(),
+        _ => version.to_string(),
+    }
+}
+
 impl Serialize for Version {
     fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
     where
@@ -143,7 +155,14 @@ fn test_date() {
 
 #[test]
 fn test_unknown() {
-    let versions = ["", "unknown", "23/01/23"];
+    let versions = [
+        "",
+        "unknown",
+        "23/01/23",
+        "2024.08.06",
+        "100.0.0",
+        "v1.2.100",
+    ];
     for v in versions {
         match Version::try_from(v).expect("ok") {
             Version::Unknown(s) => assert_eq!(v, s),
@@ -151,3 +170,11 @@ fn test_unknown() {
         }
     }
 }
+
+#[test]
+fn test_normalize_tag() {
+    assert_eq!(normalize_tag("0.32.0"), "v0.32.0");
+    assert_eq!(normalize_tag("v0.32.0"), "v0.32.0");
+    assert_eq!(normalize_tag("2024.08.06"), "2024.08.06");
+    assert_eq!(normalize_tag("100.0.0"), "100.0.0");
+}
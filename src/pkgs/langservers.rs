@@ -1,22 +1,19 @@
-use super::gh_client;
+use super::{go_installer, http_client, npm_installer, pip_installer};
 use crate::config::Config;
-use crate::pkg::{
-    CallbackOperation, Dirs, GithubReleaseInstaller, GoInstaller, NpmInstaller, Package,
-    PipInstaller, PkgInfo,
-};
+use crate::pkg::{CallbackOperation, Dirs, GithubReleaseInstaller, Package, PkgInfo};
 use crate::{pkg_info, util};
 use std::fs;
 use std::path::Path;
 
 pub fn packages(cfg: &Config) -> Vec<Package> {
     let mut packages = vec![
-        gopls(),
+        gopls(cfg),
         elixir_ls(cfg),
         vscode_langservers_extracted(cfg),
-        typescript_ls(),
-        pyright(),
-        bash_ls(),
-        pylsp(),
+        typescript_ls(cfg),
+        pyright(cfg),
+        bash_ls(cfg),
+        pylsp(cfg),
     ];
 
     let maybe_packages = vec![rust_analyzer(cfg), clojure_lsp(cfg)];
@@ -25,14 +22,14 @@ pub fn packages(cfg: &Config) -> Vec<Package> {
     packages
 }
 
-fn gopls() -> Package {
+fn gopls(cfg: &Config) -> Package {
     let args = pkg_info!(
         "https://github.com/golang/tools",
         "gopls",
         "golang.org/x/tools/gopls",
         "gopls"
     );
-    let installer = Box::new(GoInstaller {});
+    let installer = go_installer(cfg);
     Package::new(args, None, Some(installer))
 }
 
@@ -56,7 +53,7 @@ fn elixir_ls(cfg: &Config) -> Package {
         info,
         Some(Box::new(GithubReleaseInstaller::new(
             "^elixir-ls-v.*\\.zip$".to_string(),
-            gh_client(cfg),
+            http_client(cfg),
             Box::new(callback),
         ))),
         None,
@@ -74,7 +71,8 @@ fn rust_analyzer(cfg: &Config) -> Option<Package> {
         Ok(())
     };
 
-    let asset_regex = if cfg!(all(
+    // The asset names have no version.
+    let asset_name = if cfg!(all(
         target_os = "linux",
         target_arch = "x86_64",
         target_env = "musl"
@@ -90,16 +88,17 @@ fn rust_analyzer(cfg: &Config) -> Option<Package> {
         None
     };
 
-    asset_regex.map(|pattern| {
+    asset_name.map(|name| {
         Package::new(
             args,
             Some(Box::new(GithubReleaseInstaller::new(
-                pattern.to_string(),
-                gh_client(cfg),
+                format!("^{}$", regex::escape(name)),
+                http_client(cfg),
                 Box::new(callback),
             ))),
             None,
         )
+        .with_asset_template(name)
     })
 }
 
@@ -129,7 +128,7 @@ fn clojure_lsp(cfg: &Config) -> Option<Package> {
             args,
             Some(Box::new(GithubReleaseInstaller::new(
                 pattern.to_string(),
-                gh_client(cfg),
+                http_client(cfg),
                 Box::new(callback),
             ))),
             None,
@@ -137,7 +136,7 @@ fn clojure_lsp(cfg: &Config) -> Option<Package> {
     })
 }
 
-fn vscode_langservers_extracted(_cfg: &Config) -> Package {
+fn vscode_langservers_extracted(cfg: &Config) -> Package {
     let args = pkg_info!(
         "https://github.com/hrsh7th/vscode-langservers-extracted",
         "vscode-langservers-extracted"
@@ -172,44 +171,44 @@ fn vscode_langservers_extracted(_cfg: &Config) -> Package {
         Ok(())
     });
 
-    let installer = Box::new(NpmInstaller::new(vec![], callback));
+    let installer = npm_installer(cfg, vec![], callback);
     Package::new(args, None, Some(installer))
 }
 
-fn typescript_ls() -> Package {
+fn typescript_ls(cfg: &Config) -> Package {
     let args = pkg_info!(
         "https://github.com/typescript-language-server/typescript-language-server",
         "typescript-language-server"
     );
     let callback = Box::new(|_op: CallbackOperation, _info: &PkgInfo, _dirs: &Dirs| Ok(()));
-    let installer = Box::new(NpmInstaller::new(vec!["typescript".to_string()], callback));
+    let installer = npm_installer(cfg, vec!["typescript".to_string()], callback);
     Package::new(args, None, Some(installer))
 }
 
-fn pyright() -> Package {
+fn pyright(cfg: &Config) -> Package {
     let args = pkg_info!("https://github.com/microsoft/pyright", "pyright");
     let callback = Box::new(|_op: CallbackOperation, _info: &PkgInfo, _dirs: &Dirs| Ok(()));
-    let installer = Box::new(NpmInstaller::new(vec![], callback));
+    let installer = npm_installer(cfg, vec![], callback);
     Package::new(args, None, Some(installer))
 }
 
-fn bash_ls() -> Package {
+fn bash_ls(cfg: &Config) -> Package {
     let args = pkg_info!(
         "https://github.com/bash-lsp/bash-language-server",
         "bash-language-server"
     );
     let callback = Box::new(|_op: CallbackOperation, _info: &PkgInfo, _dirs: &Dirs| Ok(()));
-    let installer = Box::new(NpmInstaller::new(vec![], callback));
+    let installer = npm_installer(cfg, vec![], callback);
     Package::new(args, None, Some(installer))
 }
 
-pub fn pylsp() -> Package {
+pub fn pylsp(cfg: &Config) -> Package {
     let args = pkg_info!(
         "https://github.com/python-lsp/python-lsp-server",
         "pylsp",
         "python-lsp-server",
         "pylsp"
     );
-    let installer = Box::new(PipInstaller::new(vec![]));
+    let installer = pip_installer(cfg, vec![]);
     Package::new(args, None, Some(installer))
 }
@@ -1,18 +1,19 @@
+use super::go_installer;
 use crate::config::Config;
-use crate::pkg::{GoInstaller, Package};
+use crate::pkg::Package;
 use crate::pkg_info;
 
-pub fn packages(_cfg: &Config) -> Vec<Package> {
-    vec![actionlint()]
+pub fn packages(cfg: &Config) -> Vec<Package> {
+    vec![actionlint(cfg)]
 }
 
-fn actionlint() -> Package {
+fn actionlint(cfg: &Config) -> Package {
     let args = pkg_info!(
         "https://github.com/rhysd/actionlint",
         "actionlint",
         "github.com/rhysd/actionlint/cmd/actionlint",
         "actionlint"
     );
-    let installer = Box::new(GoInstaller {});
+    let installer = go_installer(cfg);
     Package::new(args, None, Some(installer))
 }
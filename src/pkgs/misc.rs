@@ -1,6 +1,6 @@
-use super::gh_client;
+use super::{cargo_installer, go_installer, http_client};
 use crate::config::Config;
-use crate::pkg::{CargoInstaller, Dirs, GithubReleaseInstaller, GoInstaller, Package, PkgInfo};
+use crate::pkg::{Dirs, GithubReleaseInstaller, Package, PkgInfo};
 use crate::{pkg_info, util};
 use anyhow::bail;
 use std::fs;
@@ -13,8 +13,8 @@ pub fn packages(cfg: &Config) -> Vec<Package> {
         fd(cfg),
         just(cfg),
         exa(cfg),
-        lazygit(),
-        goimports(),
+        lazygit(cfg),
+        goimports(cfg),
     ];
 
     let maybe_packages = vec![direnv(cfg), broot(cfg)];
@@ -63,17 +63,17 @@ fn nushell(cfg: &Config) -> Package {
     )) {
         "nu-.*-x86_64-linux-gnu-full.tar.gz"
     } else {
-        return Package::new(info, None, Some(Box::new(CargoInstaller {})));
+        return Package::new(info, None, Some(cargo_installer(cfg)));
     };
 
     Package::new(
         info,
         Some(Box::new(GithubReleaseInstaller::new(
             asset_regex.to_string(),
-            gh_client(cfg),
+            http_client(cfg),
             Box::new(callback),
         ))),
-        Some(Box::new(CargoInstaller {})),
+        Some(cargo_installer(cfg)),
     )
 }
 
@@ -104,31 +104,38 @@ fn bat(cfg: &Config) -> Package {
         }
     };
 
-    let asset_regex = if cfg!(all(
+    let (asset_regex, template) = if cfg!(all(
         target_os = "linux",
         target_arch = "x86_64",
         target_env = "musl"
     )) {
-        "bat-.*-x86_64-unknown-linux-musl.tar.gz"
+        (
+            "bat-.*-x86_64-unknown-linux-musl.tar.gz",
+            "bat-{tag}-x86_64-unknown-linux-musl.tar.gz",
+        )
     } else if cfg!(all(
         target_os = "linux",
         target_arch = "x86_64",
         target_env = "gnu"
     )) {
-        "bat-.*-x86_64-unknown-linux-gnu.tar.gz"
+        (
+            "bat-.*-x86_64-unknown-linux-gnu.tar.gz",
+            "bat-{tag}-x86_64-unknown-linux-gnu.tar.gz",
+        )
     } else {
-        return Package::new(info, None, Some(Box::new(CargoInstaller {})));
+        return Package::new(info, None, Some(cargo_installer(cfg)));
     };
 
     Package::new(
         info,
         Some(Box::new(GithubReleaseInstaller::new(
             asset_regex.to_string(),
-            gh_client(cfg),
+            http_client(cfg),
             Box::new(callback),
         ))),
-        Some(Box::new(CargoInstaller {})),
+        Some(cargo_installer(cfg)),
     )
+    .with_asset_template(template)
 }
 
 fn just(cfg: &Config) -> Package {
@@ -151,17 +158,17 @@ fn just(cfg: &Config) -> Package {
     let asset_regex = if cfg!(all(target_os = "linux", target_arch = "x86_64",)) {
         "just-.*-x86_64-unknown-linux-musl.tar.gz"
     } else {
-        return Package::new(info, None, Some(Box::new(CargoInstaller {})));
+        return Package::new(info, None, Some(cargo_installer(cfg)));
     };
 
     Package::new(
         info,
         Some(Box::new(GithubReleaseInstaller::new(
             asset_regex.to_string(),
-            gh_client(cfg),
+            http_client(cfg),
             Box::new(callback),
         ))),
-        Some(Box::new(CargoInstaller {})),
+        Some(cargo_installer(cfg)),
     )
 }
 
@@ -191,17 +198,17 @@ fn exa(cfg: &Config) -> Package {
     } else if cfg!(all(target_os = "linux", target_arch = "x86_64")) {
         "exa-linux-x86_64-.*.zip"
     } else {
-        return Package::new(info, None, Some(Box::new(CargoInstaller {})));
+        return Package::new(info, None, Some(cargo_installer(cfg)));
     };
 
     Package::new(
         info,
         Some(Box::new(GithubReleaseInstaller::new(
             asset_regex.to_string(),
-            gh_client(cfg),
+            http_client(cfg),
             Box::new(callback),
         ))),
-        Some(Box::new(CargoInstaller {})),
+        Some(cargo_installer(cfg)),
     )
 }
 
@@ -232,31 +239,38 @@ fn fd(cfg: &Config) -> Package {
         }
     };
 
-    let asset_regex = if cfg!(all(
+    let (asset_regex, template) = if cfg!(all(
         target_os = "linux",
         target_arch = "x86_64",
         target_env = "musl"
     )) {
-        "fd-.*-x86_64-unknown-linux-musl.tar.gz"
+        (
+            "fd-.*-x86_64-unknown-linux-musl.tar.gz",
+            "fd-{tag}-x86_64-unknown-linux-musl.tar.gz",
+        )
     } else if cfg!(all(
         target_os = "linux",
         target_arch = "x86_64",
         target_env = "gnu"
     )) {
-        "fd-.*-x86_64-unknown-linux-gnu.tar.gz"
+        (
+            "fd-.*-x86_64-unknown-linux-gnu.tar.gz",
+            "fd-{tag}-x86_64-unknown-linux-gnu.tar.gz",
+        )
     } else {
-        return Package::new(info, None, Some(Box::new(CargoInstaller {})));
+        return Package::new(info, None, Some(cargo_installer(cfg)));
     };
 
     Package::new(
         info,
         Some(Box::new(GithubReleaseInstaller::new(
             asset_regex.to_string(),
-            gh_client(cfg),
+            http_client(cfg),
             Box::new(callback),
         ))),
-        Some(Box::new(CargoInstaller {})),
+        Some(cargo_installer(cfg)),
     )
+    .with_asset_template(template)
 }
 
 fn direnv(cfg: &Config) -> Option<Package> {
@@ -284,7 +298,7 @@ fn direnv(cfg: &Config) -> Option<Package> {
             args,
             Some(Box::new(GithubReleaseInstaller::new(
                 pattern.to_string(),
-                gh_client(cfg),
+                http_client(cfg),
                 Box::new(callback),
             ))),
             None,
@@ -323,7 +337,7 @@ fn broot(cfg: &Config) -> Option<Package> {
             args,
             Some(Box::new(GithubReleaseInstaller::new(
                 pattern.to_string(),
-                gh_client(cfg),
+                http_client(cfg),
                 Box::new(callback),
             ))),
             None,
@@ -331,24 +345,24 @@ fn broot(cfg: &Config) -> Option<Package> {
     })
 }
 
-fn lazygit() -> Package {
+fn lazygit(cfg: &Config) -> Package {
     let args = pkg_info!(
         "https://github.com/jesseduffield/lazygit",
         "lazygit",
         "github.com/jesseduffield/lazygit",
         "lazygit"
     );
-    let installer = Box::new(GoInstaller {});
+    let installer = go_installer(cfg);
     Package::new(args, None, Some(installer))
 }
 
-fn goimports() -> Package {
+fn goimports(cfg: &Config) -> Package {
     let args = pkg_info!(
         "https://github.com/golang/tools",
         "goimports",
         "golang.org/x/tools/cmd/goimports",
         "goimports"
     );
-    let installer = Box::new(GoInstaller {});
+    let installer = go_installer(cfg);
     Package::new(args, None, Some(installer))
 }
@@ -1,6 +1,8 @@
-use crate::config::Config;
-use crate::github::GitHubClient;
-use crate::pkg::Package;
+use crate::config::{self, Config};
+use crate::http::HttpClient;
+use crate::pkg::{
+    CargoInstaller, GoInstaller, NpmInstaller, Package, PackageCallback, PipInstaller,
+};
 use anyhow::Result;
 use std::collections::HashMap;
 
@@ -23,9 +25,54 @@ pub fn get_packages(cfg: &Config) -> Result<Packages> {
         pkgs.insert(pkg.name().to_string(), pkg);
     }
 
+    for (name, pkg_config) in &cfg.package_configs {
+        if let Some(pkg) = pkgs.get_mut(name) {
+            if let Some(repo) = &pkg_config.repo {
+                pkg.set_repo(repo);
+            }
+            pkg.set_require_checksum(pkg_config.require_checksum);
+            if let Some(signing) = &pkg_config.signature {
+                pkg.set_signing(Some(signing.clone()));
+            }
+            if let Some(template) = &pkg_config.asset_template {
+                pkg.set_asset_template(template);
+            }
+            if let Some(source) = &pkg_config.url_source {
+                pkg.set_url_source(source.clone());
+            }
+            if let Some(api_url) = &pkg_config.gitea {
+                pkg.set_gitea(api_url);
+            }
+        }
+    }
+
     Ok(pkgs)
 }
 
-fn gh_client(cfg: &Config) -> Box<GitHubClient> {
-    Box::new(GitHubClient::new(cfg))
+fn http_client(cfg: &Config) -> Box<HttpClient> {
+    Box::new(HttpClient::new(cfg))
+}
+
+fn npm_installer(
+    cfg: &Config,
+    dependencies: Vec<String>,
+    callback: Box<PackageCallback>,
+) -> Box<NpmInstaller> {
+    let registry = cfg.mirrors.mirror(config::NPM_REGISTRY);
+    Box::new(NpmInstaller::new(dependencies, callback).with_registry(registry))
+}
+
+fn pip_installer(cfg: &Config, dependencies: Vec<String>) -> Box<PipInstaller> {
+    let index_url = cfg.mirrors.mirror(config::PYPI_INDEX);
+    Box::new(PipInstaller::new(dependencies).with_index_url(index_url))
+}
+
+fn cargo_installer(cfg: &Config) -> Box<CargoInstaller> {
+    let index = cfg.mirrors.mirror(config::CRATES_INDEX);
+    Box::new(CargoInstaller::default().with_index(index))
+}
+
+fn go_installer(cfg: &Config) -> Box<GoInstaller> {
+    let proxy = cfg.mirrors.mirror(config::GOPROXY);
+    Box::new(GoInstaller::default().with_proxy(proxy))
 }
@@ -0,0 +1,146 @@
+use crate::util;
+use crossterm::{cursor, queue, terminal};
+use std::io::{self, IsTerminal, Write};
+use std::time::{Duration, Instant};
+
+/// Minimum time between redraws of the bar.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A progress bar of a download, drawn on stderr when it's a terminal.
+pub struct Progress {
+    name: String,
+    /// Total size, if known.
+    total: Option<u64>,
+    done: u64,
+    /// Bytes already downloaded when resuming, not counted in the rate.
+    resumed: u64,
+    start: Instant,
+    last_draw: Option<Instant>,
+    enabled: bool,
+}
+
+impl Progress {
+    pub fn new(name: &str, total: Option<u64>, resumed: u64) -> Self {
+        Self {
+            name: name.to_string(),
+            total,
+            done: resumed,
+            resumed,
+            start: Instant::now(),
+            last_draw: None,
+            enabled: io::stderr().is_terminal(),
+        }
+    }
+
+    pub fn add(&mut self, bytes: u64) {
+        self.done += bytes;
+
+        let due = match self.last_draw {
+            Some(last) => last.elapsed() >= REDRAW_INTERVAL,
+            None => true,
+        };
+        if due {
+            self.draw();
+        }
+    }
+
+    /// Draws the final state of the bar and moves to the next line.
+    pub fn finish(&mut self) {
+        if self.enabled {
+            self.draw();
+            eprintln!();
+            self.enabled = false;
+        }
+    }
+
+    fn draw(&mut self) {
+        if !self.enabled {
+            return;
+        }
+        self.last_draw = Some(Instant::now());
+
+        let width = terminal::size().map(|(w, _)| w as usize).unwrap_or(80);
+        let rate = (self.done - self.resumed) as f64 / self.start.elapsed().as_secs_f64();
+        let line = render(&self.name, self.done, self.total, rate, width);
+
+        let mut stderr = io::stderr();
+        let _ = queue!(
+            stderr,
+            cursor::MoveToColumn(0),
+            terminal::Clear(terminal::ClearType::CurrentLine)
+        );
+        let _ = write!(stderr, "{}", line);
+        let _ = stderr.flush();
+    }
+}
+
+impl Drop for Progress {
+    /// Leaves the line of an interrupted download.
+    fn drop(&mut self) {
+        if self.enabled && self.last_draw.is_some() {
+            eprintln!();
+        }
+    }
+}
+
+/// Renders the bar, e.g.
+/// `nu.tar.gz [#######.......] 12.0 MiB / 40.0 MiB  3.0 MiB/s  ETA 9s`.
+fn render(name: &str, done: u64, total: Option<u64>, rate: f64, width: usize) -> String {
+    let rate_str = if rate.is_finite() && rate > 0.0 {
+        format!("{}/s", util::format_size(rate as u64))
+    } else {
+        "-".to_string()
+    };
+
+    let total = match total {
+        Some(total) if total > 0 => total,
+        _ => return format!("{} {}  {}", name, util::format_size(done), rate_str),
+    };
+
+    let eta = if rate.is_finite() && rate > 0.0 {
+        let secs = total.saturating_sub(done) as f64 / rate;
+        util::format_duration(Duration::from_secs(secs.ceil() as u64))
+    } else {
+        "-".to_string()
+    };
+
+    let stats = format!(
+        "{} / {}  {}  ETA {}",
+        util::format_size(done),
+        util::format_size(total),
+        rate_str,
+        eta
+    );
+
+    // The bar takes what's left of the line, within reason.
+    let bar_width = width
+        .saturating_sub(name.len() + stats.len() + 4)
+        .clamp(10, 40);
+    let filled = (bar_width as u64 * done.min(total) / total) as usize;
+
+    format!(
+        "{} [{}{}] {}",
+        name,
+        "#".repeat(filled),
+        ".".repeat(bar_width - filled),
+        stats
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let mib = 1024 * 1024;
+        let line = render("nu.tar.gz", 10 * mib, Some(40 * mib), 5.0 * mib as f64, 0);
+        assert_eq!(
+            line,
+            "nu.tar.gz [##........] 10.0 MiB / 40.0 MiB  5.0 MiB/s  ETA 6s"
+        );
+
+        let line = render("nu.tar.gz", 512, None, 0.0, 80);
+        assert_eq!(line, "nu.tar.gz 512 B  -");
+    }
+}
@@ -0,0 +1,538 @@
+use crate::http::HttpClient;
+use crate::pkg::version::normalize_tag;
+use crate::pkg::{Package, Release};
+use crate::source::ReleaseSource;
+use anyhow::{anyhow, bail, Result};
+use serde::Deserialize;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// The registry a package manager installs a package from, whose
+/// versions may differ from the tags of the repo of the package.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Registry {
+    /// An npm registry, e.g. `https://registry.npmjs.org/`.
+    Npm(String),
+    /// The JSON API of a Python package index, e.g. `https://pypi.org/pypi/`.
+    PyPI(String),
+    /// A sparse index of crates, e.g. `https://index.crates.io/`.
+    Crates(String),
+    /// A Go module proxy, e.g. `https://proxy.golang.org/`.
+    GoProxy(String),
+}
+
+/// Gives the URL of the JSON API of a Python package index from the URL
+/// of its simple index, e.g. `https://pypi.org/pypi/` of
+/// `https://pypi.org/simple/`, as PyPI, Artifactory and Nexus serve both.
+pub fn pypi_json_url(index_url: &str) -> String {
+    let url = index_url.trim_end_matches('/');
+    let base = url.strip_suffix("/simple").unwrap_or(url);
+    format!("{}/pypi/", base)
+}
+
+/// Accepts the abbreviated npm package document, which only has what's
+/// needed to install a package, preferring it over the full document,
+/// which can be several MB, like npm does.
+const NPM_ABBREVIATED: &str =
+    "application/vnd.npm.install-v1+json; q=1.0, application/json; q=0.8, */*";
+
+/// Resolves the releases of packages installed with npm, pip, cargo
+/// or Go from the versions published to their registry.
+pub struct RegistryClient {
+    http: Arc<HttpClient>,
+}
+
+impl RegistryClient {
+    pub fn new(http: Arc<HttpClient>) -> Self {
+        Self { http }
+    }
+
+    /// Gives the latest version of the package, and every version
+    /// of it newest first.
+    fn versions(&self, pkg: &Package) -> Result<(Option<String>, Vec<String>)> {
+        let registry = pkg
+            .registry()
+            .ok_or_else(|| anyhow!("{} has no registry", pkg.name()))?;
+
+        match &registry {
+            Registry::Npm(url) => self.npm_versions(url, pkg.mod_name()),
+            Registry::PyPI(url) => self.pypi_versions(url, pkg.mod_name()),
+            Registry::Crates(url) => self.crate_versions(url, pkg.mod_name()),
+            Registry::GoProxy(url) => self.go_versions(url, pkg.mod_name()),
+        }
+    }
+
+    fn get(&self, url: &str) -> Result<String> {
+        self.get_as(url, "application/json")
+    }
+
+    fn get_as(&self, url: &str, mime: &str) -> Result<String> {
+        match self.http.get_json_as(url, mime)? {
+            Some(body) => Ok(body),
+            None => bail!("package not found in registry: {}", url),
+        }
+    }
+
+    fn npm_versions(&self, url: &str, name: &str) -> Result<(Option<String>, Vec<String>)> {
+        let url = format!("{}/{}", url.trim_end_matches('/'), name.replace('/', "%2F"));
+        let packument: Packument = serde_json::from_str(&self.get_as(&url, NPM_ABBREVIATED)?)?;
+
+        // The abbreviated document has no publish times, which
+        // registries that only serve the full one do give.
+        let mut versions: Vec<String> = packument.versions.into_keys().collect();
+        if packument.time.is_empty() {
+            sort_semver(&mut versions);
+        } else {
+            versions.sort_by_key(|version| Reverse(packument.time.get(version).cloned()));
+        }
+        Ok((packument.dist_tags.get("latest").cloned(), versions))
+    }
+
+    fn pypi_versions(&self, url: &str, name: &str) -> Result<(Option<String>, Vec<String>)> {
+        let url = format!("{}/{}/json", url.trim_end_matches('/'), pypi_name(name));
+        let project: PyPIProject = serde_json::from_str(&self.get(&url)?)?;
+
+        let mut uploads: Vec<(String, String)> = project
+            .releases
+            .into_iter()
+            .filter_map(|(version, files)| {
+                // Versions without files, or with only yanked
+                // ones, can't be installed.
+                files
+                    .iter()
+                    .filter(|file| !file.yanked)
+                    .map(|file| file.upload_time_iso_8601.clone())
+                    .min()
+                    .map(|uploaded| (version, uploaded))
+            })
+            .collect();
+        uploads.sort_by(|a, b| b.1.cmp(&a.1));
+        let versions = uploads.into_iter().map(|(version, _)| version).collect();
+        Ok((Some(project.info.version), versions))
+    }
+
+    fn crate_versions(&self, url: &str, name: &str) -> Result<(Option<String>, Vec<String>)> {
+        let url = format!("{}/{}", url.trim_end_matches('/'), crate_path(name));
+        let body = self.get(&url)?;
+
+        // The index has a line of JSON per published version.
+        let mut versions = Vec::new();
+        for line in body.lines().filter(|line| !line.trim().is_empty()) {
+            let entry: CrateVersion = serde_json::from_str(line)?;
+            if !entry.yanked {
+                versions.push(entry.vers);
+            }
+        }
+        sort_semver(&mut versions);
+
+        let latest = versions.iter().find(|v| !is_prerelease(v)).cloned();
+        Ok((latest, versions))
+    }
+
+    fn go_versions(&self, url: &str, path: &str) -> Result<(Option<String>, Vec<String>)> {
+        let url = url.trim_end_matches('/');
+
+        // Commands are packages within a module, e.g. golang.org/x/tools/cmd/goimports
+        // of golang.org/x/tools, so the module is found by walking up the path.
+        let mut module = path;
+        let list = loop {
+            let list_url = format!("{}/{}/@v/list", url, escape_module(module));
+            if let Some(list) = self.http.get_json(&list_url)? {
+                break list;
+            }
+            match module.rsplit_once('/') {
+                Some((parent, _)) if parent.contains('/') => module = parent,
+                _ => bail!("module not found in proxy: {}", path),
+            }
+        };
+
+        let mut versions: Vec<String> = list
+            .lines()
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty())
+            .collect();
+        sort_semver(&mut versions);
+
+        // Modules without tagged versions have a pseudo-version of their latest commit.
+        let latest = match versions.iter().find(|v| !is_prerelease(v)) {
+            Some(latest) => Some(latest.clone()),
+            None => {
+                let latest_url = format!("{}/{}/@latest", url, escape_module(module));
+                match self.http.get_json(&latest_url)? {
+                    Some(body) => Some(serde_json::from_str::<GoVersion>(&body)?.version),
+                    None => None,
+                }
+            }
+        };
+        Ok((latest, versions))
+    }
+}
+
+impl ReleaseSource for RegistryClient {
+    fn latest(&self, pkg: &Package) -> Result<Option<Release>> {
+        let (latest, _) = self.versions(pkg)?;
+        Ok(latest.map(|version| release(&version)))
+    }
+
+    fn get_from_tag(&self, pkg: &Package, tag: &str) -> Result<Option<Release>> {
+        let (_, versions) = self.versions(pkg)?;
+        Ok(versions
+            .iter()
+            .find(|version| *version == tag || normalize_tag(version) == tag)
+            .map(|version| release(version)))
+    }
+
+    fn list(&self, pkg: &Package) -> Result<Vec<Release>> {
+        let (_, versions) = self.versions(pkg)?;
+        Ok(versions.iter().map(|version| release(version)).collect())
+    }
+}
+
+/// Gives the release of a published version, which has no assets
+/// as the package manager downloads the package itself. The tag is
+/// the version as published, to be installed exactly as such.
+fn release(version: &str) -> Release {
+    Release {
+        name: version.to_string(),
+        tag: version.to_string(),
+        prerelease: is_prerelease(version),
+        assets: vec![],
+    }
+}
+
+/// Tells if the version is a prerelease, e.g. `5.0.0-beta.1` on npm or
+/// `2.0.0rc1` on PyPI, though not a post-release like `1.0.post1`. Go
+/// pseudo-versions, e.g. `v0.0.0-20240101000000-abcdef123456`, count too.
+fn is_prerelease(version: &str) -> bool {
+    let version = version.to_lowercase();
+    let version = version.trim_start_matches('v');
+    let version = version.split(".post").next().unwrap_or_default();
+    version.contains('-') || version.contains(|c: char| c.is_ascii_alphabetic())
+}
+
+/// Sorts semantic versions newest first, releases before their prereleases.
+fn sort_semver(versions: &mut [String]) {
+    versions.sort_by_cached_key(|version| {
+        let version = version.trim_start_matches('v');
+        let (core, pre) = match version.split_once('-') {
+            Some((core, pre)) => (core, Some(pre)),
+            None => (version.split('+').next().unwrap_or_default(), None),
+        };
+        let numbers: Vec<u64> = core.split('.').filter_map(|n| n.parse().ok()).collect();
+        Reverse((numbers, pre.is_none(), pre.map(|pre| pre.to_string())))
+    });
+}
+
+/// Gives the path of a crate in a sparse index, e.g. `3/s/syn`
+/// or `ri/pg/ripgrep`.
+fn crate_path(name: &str) -> String {
+    let name = name.to_lowercase();
+    match name.len() {
+        1 => format!("1/{}", name),
+        2 => format!("2/{}", name),
+        3 => format!("3/{}/{}", &name[..1], name),
+        _ => format!("{}/{}/{}", &name[..2], &name[2..4], name),
+    }
+}
+
+/// Escapes a module path for a Go module proxy, which
+/// writes upper case letters as `!` and the lower case one.
+fn escape_module(path: &str) -> String {
+    let mut escaped = String::with_capacity(path.len());
+    for c in path.chars() {
+        if c.is_ascii_uppercase() {
+            escaped.push('!');
+            escaped.push(c.to_ascii_lowercase());
+        } else {
+            escaped.push(c);
+        }
+    }
+    escaped
+}
+
+/// Gives the normalized name of a project on PyPI, e.g.
+/// `python-lsp-server` of `Python_LSP.Server`.
+fn pypi_name(name: &str) -> String {
+    name.to_lowercase()
+        .split(['-', '_', '.'])
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// The document of an npm package, listing its versions.
+#[derive(Deserialize)]
+struct Packument {
+    #[serde(rename = "dist-tags", default)]
+    dist_tags: HashMap<String, String>,
+    #[serde(default)]
+    versions: HashMap<String, serde_json::Value>,
+    /// When each version was published, by version, only in the full
+    /// document.
+    #[serde(default)]
+    time: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct PyPIProject {
+    info: PyPIInfo,
+    #[serde(default)]
+    releases: HashMap<String, Vec<PyPIFile>>,
+}
+
+#[derive(Deserialize)]
+struct PyPIInfo {
+    version: String,
+}
+
+#[derive(Deserialize)]
+struct PyPIFile {
+    upload_time_iso_8601: String,
+    #[serde(default)]
+    yanked: bool,
+}
+
+/// A line of the sparse index of a crate.
+#[derive(Deserialize)]
+struct CrateVersion {
+    vers: String,
+    #[serde(default)]
+    yanked: bool,
+}
+
+#[derive(Deserialize)]
+struct GoVersion {
+    #[serde(rename = "Version")]
+    version: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::http::testing::serve;
+    use crate::pkg::{CargoInstaller, GoInstaller, NpmInstaller, PipInstaller};
+    use crate::pkg_info;
+    use crate::source::Sources;
+
+    #[test]
+    fn test_npm() -> Result<()> {
+        let (url, server) = serve(vec![(
+            "200 OK",
+            r#"{
+                "name": "@scope/tool",
+                "modified": "2024-09-01T00:00:00.000Z",
+                "dist-tags": { "latest": "4.10.0", "next": "5.0.0-beta.1" },
+                "versions": { "4.8.1": {}, "4.10.0": {}, "5.0.0-beta.1": {} }
+            }"#,
+        )])?;
+
+        let callback = Box::new(|_, _: &_, _: &_| Ok(()));
+        let installer = NpmInstaller::new(vec![], callback).with_registry(Some(url));
+        let pkg = Package::new(
+            pkg_info!("owner/tool", "tool", "@scope/tool"),
+            None,
+            Some(Box::new(installer)),
+        );
+
+        let releases = Sources::new(&Config::default()).registry.list(&pkg)?;
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].starts_with("GET /@scope%2Ftool "));
+        assert!(requests[0]
+            .to_lowercase()
+            .contains("accept: application/vnd.npm.install-v1+json"));
+        let tags: Vec<&str> = releases.iter().map(|r| r.tag.as_str()).collect();
+        assert_eq!(tags, vec!["5.0.0-beta.1", "4.10.0", "4.8.1"]);
+        assert!(releases[0].prerelease);
+        assert!(releases[1].assets.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_pypi() -> Result<()> {
+        let (url, server) = serve(vec![(
+            "200 OK",
+            r#"{
+                "info": { "name": "python-lsp-server", "version": "1.12.0" },
+                "releases": {
+                    "1.11.0": [{ "upload_time_iso_8601": "2024-03-29T21:22:43Z", "yanked": false }],
+                    "1.12.0": [{ "upload_time_iso_8601": "2024-08-25T09:04:45Z", "yanked": false }],
+                    "1.13.0rc1": []
+                }
+            }"#,
+        )])?;
+
+        let index_url = format!("{}simple/", url);
+        let installer = PipInstaller::new(vec![]).with_index_url(Some(index_url));
+        let pkg = Package::new(
+            pkg_info!("owner/pylsp", "pylsp", "Python_LSP.Server"),
+            None,
+            Some(Box::new(installer)),
+        );
+
+        let release = Sources::new(&Config::default())
+            .registry
+            .latest(&pkg)?
+            .expect("release");
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].starts_with("GET /pypi/python-lsp-server/json "));
+        assert_eq!(release.tag, "1.12.0");
+        assert_eq!(release.name, "1.12.0");
+        assert!(!release.prerelease);
+        Ok(())
+    }
+
+    #[test]
+    fn test_crates() -> Result<()> {
+        let (url, server) = serve(vec![(
+            "200 OK",
+            r#"{"name":"ripgrep","vers":"13.0.0","deps":[],"cksum":"","features":{},"yanked":false}
+{"name":"ripgrep","vers":"14.1.0","deps":[],"cksum":"","features":{},"yanked":false}
+{"name":"ripgrep","vers":"14.1.1","deps":[],"cksum":"","features":{},"yanked":true}
+{"name":"ripgrep","vers":"15.0.0-rc.1","deps":[],"cksum":"","features":{},"yanked":false}
+"#,
+        )])?;
+
+        let installer = CargoInstaller::default().with_index(Some(url));
+        let pkg = Package::new(
+            pkg_info!("owner/rg", "ripgrep"),
+            None,
+            Some(Box::new(installer)),
+        );
+
+        let client = Sources::new(&Config::default()).registry;
+        let releases = client.list(&pkg)?;
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].starts_with("GET /ri/pg/ripgrep "));
+        let tags: Vec<&str> = releases.iter().map(|r| r.tag.as_str()).collect();
+        assert_eq!(tags, vec!["15.0.0-rc.1", "14.1.0", "13.0.0"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pypi_calver() -> Result<()> {
+        let project = r#"{
+            "info": { "name": "tool", "version": "2024.08.06" },
+            "releases": {
+                "2024.08.06": [{ "upload_time_iso_8601": "2024-08-06T10:00:00Z", "yanked": false }],
+                "2023.12.1": [{ "upload_time_iso_8601": "2023-12-01T10:00:00Z", "yanked": false }]
+            }
+        }"#;
+        let (url, server) = serve(vec![("200 OK", project), ("200 OK", project)])?;
+
+        let installer = PipInstaller::new(vec![]).with_index_url(Some(format!("{}simple/", url)));
+        let pkg = Package::new(
+            pkg_info!("owner/tool", "tool"),
+            None,
+            Some(Box::new(installer)),
+        );
+
+        let client = Sources::new(&Config::default()).registry;
+        let latest = client.latest(&pkg)?.expect("release");
+        let pinned = client.get_from_tag(&pkg, "2023.12.1")?.expect("release");
+        server.join().unwrap();
+
+        assert_eq!(latest.tag, "2024.08.06");
+        assert_eq!(latest.package_version()?, "2024.08.06");
+        assert_eq!(pinned.package_version()?, "2023.12.1");
+        Ok(())
+    }
+
+    #[test]
+    fn test_crates_large_major() -> Result<()> {
+        let (url, server) = serve(vec![(
+            "200 OK",
+            r#"{"name":"tool","vers":"99.1.0","deps":[],"cksum":"","features":{},"yanked":false}
+{"name":"tool","vers":"100.0.0","deps":[],"cksum":"","features":{},"yanked":false}
+"#,
+        )])?;
+
+        let installer = CargoInstaller::default().with_index(Some(url));
+        let pkg = Package::new(
+            pkg_info!("owner/tool", "tool"),
+            None,
+            Some(Box::new(installer)),
+        );
+
+        let release = Sources::new(&Config::default())
+            .registry
+            .latest(&pkg)?
+            .expect("release");
+        server.join().unwrap();
+
+        assert_eq!(release.tag, "100.0.0");
+        assert_eq!(release.package_version()?, "100.0.0");
+        assert_eq!(release.try_get_version()?.to_string(), "100.0.0");
+        Ok(())
+    }
+
+    #[test]
+    fn test_go_proxy() -> Result<()> {
+        let (url, server) = serve(vec![
+            ("404 Not Found", "not found"),
+            ("410 Gone", "not found"),
+            ("200 OK", "v0.23.0\nv0.24.0\nv0.9.3\nv0.25.0-pre.1\n"),
+        ])?;
+
+        let installer = GoInstaller::default().with_proxy(Some(url));
+        let pkg = Package::new(
+            pkg_info!(
+                "https://github.com/golang/tools",
+                "goimports",
+                "golang.org/x/tools/cmd/goimports"
+            ),
+            None,
+            Some(Box::new(installer)),
+        );
+
+        let release = Sources::new(&Config::default())
+            .registry
+            .latest(&pkg)?
+            .expect("release");
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].starts_with("GET /golang.org/x/tools/cmd/goimports/@v/list "));
+        assert!(requests[1].starts_with("GET /golang.org/x/tools/cmd/@v/list "));
+        assert!(requests[2].starts_with("GET /golang.org/x/tools/@v/list "));
+        assert_eq!(release.tag, "v0.24.0");
+        Ok(())
+    }
+
+    #[test]
+    fn test_paths() {
+        assert_eq!(crate_path("a"), "1/a");
+        assert_eq!(crate_path("fd"), "2/fd");
+        assert_eq!(crate_path("bat"), "3/b/bat");
+        assert_eq!(crate_path("Ripgrep"), "ri/pg/ripgrep");
+        assert_eq!(
+            escape_module("github.com/BurntSushi/toml"),
+            "github.com/!burnt!sushi/toml"
+        );
+    }
+
+    #[test]
+    fn test_pypi_json_url() {
+        assert_eq!(
+            pypi_json_url("https://pypi.org/simple/"),
+            "https://pypi.org/pypi/"
+        );
+        assert_eq!(
+            pypi_json_url("https://repo.example.com/api/pypi/pypi/simple"),
+            "https://repo.example.com/api/pypi/pypi/pypi/"
+        );
+    }
+
+    #[test]
+    fn test_is_prerelease() {
+        assert!(is_prerelease("5.0.0-beta.1"));
+        assert!(is_prerelease("2.0.0rc1"));
+        assert!(!is_prerelease("1.0.post1"));
+        assert!(!is_prerelease("1.1.386"));
+        assert!(!is_prerelease("v0.24.0"));
+        assert!(is_prerelease("v0.0.0-20240101000000-abcdef123456"));
+    }
+}
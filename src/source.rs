@@ -0,0 +1,157 @@
+use crate::config::{self, Config, HostKind};
+use crate::gitea::GiteaClient;
+use crate::github::GitHubClient;
+use crate::gitlab::GitLabClient;
+use crate::http::HttpClient;
+use crate::pkg::{Package, Release, Version};
+use crate::registry::RegistryClient;
+use crate::url_source::UrlSourceClient;
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Where the releases of packages are resolved from,
+/// e.g. GitHub or GitLab releases.
+pub trait ReleaseSource: Send + Sync {
+    /// Gives the latest release of the package, if any.
+    fn latest(&self, pkg: &Package) -> Result<Option<Release>>;
+
+    /// Gives the release of the package with the tag, if any.
+    fn get_from_tag(&self, pkg: &Package, tag: &str) -> Result<Option<Release>>;
+
+    /// Lists the releases of the package, newest first.
+    fn list(&self, pkg: &Package) -> Result<Vec<Release>>;
+
+    /// Resolves the release of the package, the latest unless a version
+    /// is given. Semantic versions are looked up with and without a `v`
+    /// prefix, as not every project prefixes their tags with it.
+    fn try_get_release(&self, pkg: &Package, version: Option<Version>) -> Result<Option<Release>> {
+        let v = match &version {
+            Some(v) => v,
+            None => return self.latest(pkg),
+        };
+
+        let version = v.to_string();
+        match self.get_from_tag(pkg, &version)? {
+            None if matches!(v, Version::Sem(..)) => {
+                self.get_from_tag(pkg, version.trim_start_matches('v'))
+            }
+            release => Ok(release),
+        }
+    }
+}
+
+/// The release sources, of which one is selected per package by the
+/// host of its repo, unless it's published to a registry or download site.
+pub struct Sources {
+    /// The HTTP client shared by the sources.
+    pub http: Arc<HttpClient>,
+    pub github: GitHubClient,
+    pub gitlab: GitLabClient,
+    pub gitea: GiteaClient,
+    pub url: UrlSourceClient,
+    pub registry: RegistryClient,
+    /// Kinds of the configured hosts.
+    kinds: HashMap<String, HostKind>,
+}
+
+impl Sources {
+    pub fn new(cfg: &Config) -> Self {
+        let http = Arc::new(HttpClient::new(cfg));
+        Self {
+            github: GitHubClient::new(cfg, http.clone()),
+            gitlab: GitLabClient::new(cfg, http.clone()),
+            gitea: GiteaClient::new(cfg, http.clone()),
+            url: UrlSourceClient::new(http.clone()),
+            registry: RegistryClient::new(http.clone()),
+            http,
+            kinds: cfg
+                .hosts
+                .iter()
+                .map(|(name, host)| (name.clone(), host.kind))
+                .collect(),
+        }
+    }
+
+    /// Gives the source of the releases of the package.
+    pub fn get(&self, pkg: &Package) -> &dyn ReleaseSource {
+        if pkg.url_source().is_some() {
+            return &self.url;
+        }
+        if pkg.registry().is_some() {
+            return &self.registry;
+        }
+
+        match self.kind(pkg) {
+            HostKind::GitHub => &self.github,
+            HostKind::GitLab => &self.gitlab,
+            HostKind::Gitea => &self.gitea,
+        }
+    }
+
+    /// Tells if the releases of the package are on GitHub.
+    pub fn is_github(&self, pkg: &Package) -> bool {
+        pkg.url_source().is_none() && pkg.registry().is_none() && self.kind(pkg) == HostKind::GitHub
+    }
+
+    /// Gives the kind of the host of the package's repo, e.g.
+    /// `https://gitlab.com/a/b`. Repos given as `owner/name` are on github.com,
+    /// unless the package gives the Gitea instance they're on.
+    pub fn kind(&self, pkg: &Package) -> HostKind {
+        if pkg.gitea().is_some() {
+            return HostKind::Gitea;
+        }
+
+        let repo = pkg.repo();
+        let host = repo
+            .strip_prefix("https://")
+            .or_else(|| repo.strip_prefix("http://"))
+            .and_then(|rest| rest.split('/').next());
+
+        match host {
+            Some(config::GITLAB) => HostKind::GitLab,
+            Some(config::CODEBERG) => HostKind::Gitea,
+            Some(host) => self.kinds.get(host).copied().unwrap_or_default(),
+            None => HostKind::GitHub,
+        }
+    }
+}
+
+/// Splits a repo into its host and owner/name, the host being
+/// `default_host` unless given, e.g. `https://git.example.com/a/b`.
+pub(crate) fn split_repo<'a>(repo: &'a str, default_host: &'a str) -> (&'a str, &'a str) {
+    let repo = repo.trim_end_matches('/').trim_end_matches(".git");
+    let rest = repo
+        .strip_prefix("https://")
+        .or_else(|| repo.strip_prefix("http://"));
+
+    match rest.and_then(|rest| rest.split_once('/')) {
+        Some((host, path)) => (host, path),
+        None => (default_host, repo),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_repo() {
+        assert_eq!(
+            split_repo("rust-lang/rust-analyzer", config::GITHUB),
+            ("github.com", "rust-lang/rust-analyzer")
+        );
+        assert_eq!(
+            split_repo("https://github.com/rust-lang/rust-analyzer", config::GITHUB),
+            ("github.com", "rust-lang/rust-analyzer")
+        );
+        assert_eq!(
+            split_repo("owner/tool", config::CODEBERG),
+            ("codeberg.org", "owner/tool")
+        );
+        assert_eq!(
+            split_repo("https://git.example.com/owner/tool.git", config::CODEBERG),
+            ("git.example.com", "owner/tool")
+        );
+    }
+}
@@ -0,0 +1,343 @@
+use crate::http::HttpClient;
+use crate::pkg::version::normalize_tag;
+use crate::pkg::{Asset, Package, Release};
+use crate::source::ReleaseSource;
+use anyhow::{anyhow, bail, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// Releases of a package published on a vendor's download site rather
+/// than a forge: the versions are read from an index, and the asset
+/// of each is downloaded from a URL template.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct UrlSource {
+    /// URL of the index listing the versions, JSON or e.g. an HTML page.
+    pub index: String,
+    /// Path to the versions in a JSON index, of keys separated by dots,
+    /// where `*` stands for every element of an array or value of an
+    /// object, e.g. `versions.*.version`.
+    #[serde(rename = "json-path")]
+    pub json_path: Option<String>,
+    /// Regex matching the versions in any other index, with a group
+    /// capturing the version, e.g. `tool_([0-9.]+)/`.
+    pub regex: Option<String>,
+    /// URL to download the asset of a version from, with `{version}`,
+    /// `{os}` and `{arch}` placeholders, e.g.
+    /// `https://go.dev/dl/{version}.{os}-{arch}.tar.gz`.
+    pub download: String,
+}
+
+impl UrlSource {
+    /// Extracts the versions from the content of the index, newest first.
+    fn versions(&self, content: &str) -> Result<Vec<String>> {
+        let mut versions = match (&self.json_path, &self.regex) {
+            (Some(path), _) => {
+                let json: Value = serde_json::from_str(content)?;
+                select(&json, path)
+            }
+            (None, Some(regex)) => {
+                let regex = Regex::new(regex)?;
+                regex
+                    .captures_iter(content)
+                    .filter_map(|c| c.get(1).or_else(|| c.get(0)))
+                    .map(|m| m.as_str().to_string())
+                    .collect()
+            }
+            (None, None) => bail!("index {} has neither a json-path nor a regex", self.index),
+        };
+
+        let mut seen = HashSet::new();
+        versions.retain(|version| seen.insert(version.clone()));
+        versions.sort_by_key(|version| Reverse(numbers(version)));
+        Ok(versions)
+    }
+
+    /// Gives the release of a version from the index.
+    fn release(&self, version: &str) -> Release {
+        let url = render(&self.download, version);
+        let name = url
+            .rsplit('/')
+            .next()
+            .unwrap_or(&url)
+            .split(['?', '#'])
+            .next()
+            .unwrap_or_default()
+            .to_string();
+
+        Release {
+            name: version.to_string(),
+            tag: normalize_tag(version),
+            prerelease: is_prerelease(version),
+            assets: vec![Asset {
+                name,
+                url,
+                sha256: None,
+            }],
+        }
+    }
+}
+
+/// Resolves the releases of packages with a `UrlSource`.
+pub struct UrlSourceClient {
+    http: Arc<HttpClient>,
+}
+
+impl UrlSourceClient {
+    pub fn new(http: Arc<HttpClient>) -> Self {
+        Self { http }
+    }
+
+    fn versions<'a>(&self, pkg: &'a Package) -> Result<(&'a UrlSource, Vec<String>)> {
+        let source = pkg
+            .url_source()
+            .ok_or_else(|| anyhow!("{} has no version index", pkg.name()))?;
+
+        let content = match self.http.get_json(&source.index)? {
+            Some(content) => content,
+            None => bail!("version index not found: {}", source.index),
+        };
+
+        Ok((source, source.versions(&content)?))
+    }
+}
+
+impl ReleaseSource for UrlSourceClient {
+    fn latest(&self, pkg: &Package) -> Result<Option<Release>> {
+        let (source, versions) = self.versions(pkg)?;
+        Ok(versions
+            .iter()
+            .find(|version| !is_prerelease(version))
+            .map(|version| source.release(version)))
+    }
+
+    fn get_from_tag(&self, pkg: &Package, tag: &str) -> Result<Option<Release>> {
+        let (source, versions) = self.versions(pkg)?;
+        Ok(versions
+            .iter()
+            .find(|version| *version == tag || normalize_tag(version) == tag)
+            .map(|version| source.release(version)))
+    }
+
+    fn list(&self, pkg: &Package) -> Result<Vec<Release>> {
+        let (source, versions) = self.versions(pkg)?;
+        Ok(versions
+            .iter()
+            .map(|version| source.release(version))
+            .collect())
+    }
+}
+
+/// Selects the strings at the path in the JSON value.
+fn select(value: &Value, path: &str) -> Vec<String> {
+    let mut values = vec![value];
+    for key in path.split('.').filter(|key| !key.is_empty()) {
+        values = values
+            .into_iter()
+            .flat_map(|value| -> Vec<&Value> {
+                match (key, value) {
+                    ("*", Value::Array(items)) => items.iter().collect(),
+                    ("*", Value::Object(map)) => map.values().collect(),
+                    (key, Value::Array(items)) => key
+                        .parse::<usize>()
+                        .ok()
+                        .and_then(|i| items.get(i))
+                        .into_iter()
+                        .collect(),
+                    (key, value) => value.get(key).into_iter().collect(),
+                }
+            })
+            .collect();
+    }
+
+    values
+        .into_iter()
+        .filter_map(|value| value.as_str().map(|s| s.to_string()))
+        .collect()
+}
+
+/// Gives the numbers in a version, e.g. `[1, 22, 5]`
+/// of `go1.22.5`, to sort versions by.
+fn numbers(version: &str) -> Vec<u64> {
+    version
+        .split(|c: char| !c.is_ascii_digit())
+        .filter_map(|n| n.parse().ok())
+        .collect()
+}
+
+/// Tells if the version is a prerelease, e.g. `0.33.0-beta1` or
+/// `go1.23rc1`, though not a date like `2024-07-29`. Markers count
+/// only after a separator or a digit, so e.g. `arcade1.0` isn't one.
+fn is_prerelease(version: &str) -> bool {
+    let version = version.to_lowercase();
+    let marked = ["alpha", "beta", "rc"].iter().any(|marker| {
+        version.match_indices(marker).any(|(i, _)| {
+            version[..i].ends_with(|c: char| c.is_ascii_digit() || matches!(c, '-' | '.' | '_'))
+        })
+    });
+    marked
+        || version
+            .split('-')
+            .skip(1)
+            .any(|part| part.starts_with(|c: char| c.is_ascii_alphabetic()))
+}
+
+/// Renders the download URL of a version, `{os}` and `{arch}` named
+/// like Go does, which most vendors follow, e.g. `linux` and `amd64`.
+fn render(template: &str, version: &str) -> String {
+    let os = match std::env::consts::OS {
+        "macos" => "darwin",
+        os => os,
+    };
+    let arch = match std::env::consts::ARCH {
+        "x86_64" => "amd64",
+        "aarch64" => "arm64",
+        "x86" => "386",
+        arch => arch,
+    };
+
+    template
+        .replace("{version}", version)
+        .replace("{os}", os)
+        .replace("{arch}", arch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::http::testing::serve;
+    use crate::pkg::GithubReleaseInstaller;
+    use crate::pkg_info;
+    use crate::source::Sources;
+
+    fn source(json_path: Option<&str>, regex: Option<&str>) -> UrlSource {
+        UrlSource {
+            index: "https://releases.example.com/tool/index.json".into(),
+            json_path: json_path.map(|s| s.to_string()),
+            regex: regex.map(|s| s.to_string()),
+            download: "https://releases.example.com/tool/{version}/tool_{version}_{os}_{arch}.zip"
+                .into(),
+        }
+    }
+
+    #[test]
+    fn test_versions_json() -> Result<()> {
+        let index = r#"{
+            "name": "tool",
+            "versions": {
+                "0.9.0": { "version": "0.9.0" },
+                "0.32.0": { "version": "0.32.0" },
+                "0.33.0-beta1": { "version": "0.33.0-beta1" }
+            }
+        }"#;
+        let versions = source(Some("versions.*.version"), None).versions(index)?;
+        assert_eq!(versions, vec!["0.33.0-beta1", "0.32.0", "0.9.0"]);
+
+        let go = r#"[{ "version": "go1.22.5" }, { "version": "go1.21.12" }]"#;
+        assert_eq!(select(&serde_json::from_str(go)?, "*.version").len(), 2);
+        assert_eq!(
+            select(&serde_json::from_str(go)?, "1.version"),
+            vec!["go1.21.12"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_versions_regex() -> Result<()> {
+        let index = r#"<ul>
+            <li><a href="/tool/0.31.5/">tool_0.31.5</a></li>
+            <li><a href="/tool/0.32.0/">tool_0.32.0</a></li>
+        </ul>"#;
+        let versions = source(None, Some(r"tool_([0-9.]+)<")).versions(index)?;
+        assert_eq!(versions, vec!["0.32.0", "0.31.5"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_release() {
+        let release = source(Some("versions.*.version"), None).release("0.32.0");
+        assert_eq!(release.tag, "v0.32.0");
+        assert!(!release.prerelease);
+
+        let asset = &release.assets[0];
+        assert!(asset.name.starts_with("tool_0.32.0_"));
+        assert!(asset
+            .url
+            .starts_with("https://releases.example.com/tool/0.32.0/tool_0.32.0_"));
+        assert!(!asset.url.contains('{'));
+    }
+
+    #[test]
+    fn test_is_prerelease() {
+        for version in [
+            "0.33.0-beta1",
+            "go1.23rc1",
+            "1.0.rc.1",
+            "2.0_alpha",
+            "1.0.0-next.2",
+        ] {
+            assert!(is_prerelease(version), "{}", version);
+        }
+        for version in [
+            "0.32.0",
+            "2024-07-29",
+            "go1.22.5",
+            "arcade1.0",
+            "sources1.2",
+        ] {
+            assert!(!is_prerelease(version), "{}", version);
+        }
+    }
+
+    #[test]
+    fn test_resolve() -> Result<()> {
+        let index = r#"{
+            "versions": {
+                "0.31.5": { "version": "0.31.5" },
+                "0.32.0": { "version": "0.32.0" },
+                "0.33.0-beta1": { "version": "0.33.0-beta1" }
+            }
+        }"#;
+        let (url, server) = serve(vec![("200 OK", index), ("200 OK", index)])?;
+
+        let cfg = Config::default();
+        let installer = GithubReleaseInstaller::new(
+            r"tool_.*\.zip".into(),
+            Box::new(HttpClient::new(&cfg)),
+            Box::new(|_, _, _| Ok(())),
+        );
+        let pkg = Package::new(
+            pkg_info!("owner/tool", "tool"),
+            Some(Box::new(installer)),
+            None,
+        )
+        .with_url_source(UrlSource {
+            index: format!("{}tool/index.json", url),
+            json_path: Some("versions.*.version".into()),
+            regex: None,
+            download: format!(
+                "{}tool/{{version}}/tool_{{version}}_{{os}}_{{arch}}.zip",
+                url
+            ),
+        });
+
+        let sources = Sources::new(&cfg);
+        assert!(!sources.is_github(&pkg));
+        let source = sources.get(&pkg);
+        let latest = source.latest(&pkg)?.expect("release");
+        let pinned = source.get_from_tag(&pkg, "v0.31.5")?.expect("release");
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].starts_with("GET /tool/index.json "));
+        assert_eq!(latest.tag, "v0.32.0");
+        assert!(latest.assets[0]
+            .url
+            .starts_with(&format!("{}tool/0.32.0/tool_0.32.0_", url)));
+        assert_eq!(pinned.name, "0.31.5");
+        Ok(())
+    }
+}
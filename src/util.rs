@@ -1,12 +1,15 @@
 use anyhow::{bail, Result};
 use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha256};
 use std::ffi::OsStr;
 use std::io::{LineWriter, Read, Write};
 use std::os::unix::prelude::PermissionsExt;
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
+use std::time::Duration;
 use std::{fs, process};
 
+use crate::archive;
 use crate::error::Error;
 
 pub fn json_from_file<T>(path: &Path) -> Result<T>
@@ -30,6 +33,79 @@ where
     Ok(t)
 }
 
+/// Returns the hex encoded SHA-256 of the bytes.
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// Returns the hex encoded SHA-256 of the file, reading it in chunks.
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut hasher = Sha256::new();
+    let mut file = fs::File::open(path)?;
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Gives an identifier of the platform dti was built for,
+/// e.g. x86_64-linux-gnu.
+pub fn platform() -> String {
+    let arch = std::env::consts::ARCH;
+    let os = std::env::consts::OS;
+    if cfg!(target_env = "musl") {
+        format!("{}-{}-musl", arch, os)
+    } else if cfg!(target_env = "gnu") {
+        format!("{}-{}-gnu", arch, os)
+    } else {
+        format!("{}-{}", arch, os)
+    }
+}
+
+/// Gives the number of bytes used by the file, or all
+/// files below it if it's a directory. Symlinks are not followed.
+pub fn disk_usage(path: &Path) -> Result<u64> {
+    let metadata = fs::symlink_metadata(path)?;
+    if !metadata.is_dir() {
+        return Ok(metadata.len());
+    }
+
+    let mut size = 0;
+    for entry in fs::read_dir(path)? {
+        size += disk_usage(&entry?.path())?;
+    }
+    Ok(size)
+}
+
+/// Formats a number of bytes for humans, e.g. 1.5 MiB.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Formats a duration for humans, e.g. 12m 3s.
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (h, m, s) = (secs / 3600, secs % 3600 / 60, secs % 60);
+
+    if h > 0 {
+        format!("{}h {}m", h, m)
+    } else if m > 0 {
+        format!("{}m {}s", m, s)
+    } else {
+        format!("{}s", s)
+    }
+}
+
 /// Ensures that a command, e.g. pip, is installed.
 pub fn require_command(cmd: &'static str) -> Result<(), Error> {
     match which::which(cmd) {
@@ -125,38 +201,40 @@ pub fn symlink(original: &Path, link: &Path) -> Result<()> {
     Ok(())
 }
 
-/// Decompress an archive (zip or tar.gz) at `path` to `outdir`.
+/// Decompress an archive (zip or tar, optionally compressed with gzip, xz,
+/// bzip2 or zstd) at `path` to the directory `outpath`, or a single
+/// compressed file, e.g. `.gz`, to the file `outpath`.
 pub fn decompress(path: &Path, outpath: &Path) -> Result<()> {
-    let fname = match path.file_name() {
-        Some(ext) => ext,
-        None => bail!("decompress: unable to identify archive type"),
-    };
-
-    let fname = match fname.to_str() {
-        Some(ext) => ext,
+    let fname = match path.file_name().and_then(|fname| fname.to_str()) {
+        Some(fname) => fname,
         None => bail!("decompress: unable to identify archive type"),
     };
 
+    // Downloads aren't trusted, so entries leading outside of
+    // `outpath` and decompression bombs are refused.
+    let limits = archive::Limits::default();
     let file = fs::File::open(path)?;
-    if fname.ends_with("zip") {
-        let mut archive = zip::ZipArchive::new(file)?;
-        archive.extract(outpath)?;
-    } else if fname.ends_with("tar.gz") {
-        let tar = flate2::read::GzDecoder::new(file);
-        let mut archive = tar::Archive::new(tar);
-        archive.unpack(outpath)?;
-    } else if fname.ends_with("gz") {
-        let mut tar = flate2::read::GzDecoder::new(file);
-        let mut buf: Vec<u8> = Vec::new();
-        tar.read_to_end(&mut buf)?;
-        write_file(outpath, &buf)?
-    } else {
-        bail!("decompress: unable to identify archive type");
+    match archive::Format::of(path)? {
+        archive::Format::Zip => archive::unzip(file, outpath, limits)?,
+        archive::Format::Tar(compression) => {
+            archive::untar(compression.decoder(file)?, outpath, limits)?
+        }
+        archive::Format::File(compression) => {
+            archive::unpack_file(compression.decoder(file)?, outpath, fname, limits)?
+        }
     }
 
     Ok(())
 }
 
+/// Checks if the file at path is executable by anyone.
+pub fn is_executable(path: &Path) -> bool {
+    match fs::metadata(path) {
+        Ok(m) => m.permissions().mode() & 0o111 != 0,
+        Err(_) => false,
+    }
+}
+
 #[allow(unused)]
 pub fn make_executable(path: &Path) -> Result<()> {
     let file = fs::OpenOptions::new().read(true).open(path)?;
@@ -207,6 +285,37 @@ mod tests {
         assert!(err.is_err());
     }
 
+    #[test]
+    fn test_sha256_hex() {
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_sha256_file() -> Result<()> {
+        let dir = tempfile::tempdir_in(".")?;
+        let path = dir.path().join("abc");
+        fs::write(&path, b"abc")?;
+        assert_eq!(sha256_file(&path)?, sha256_hex(b"abc"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(12), "12 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(40 * 1024 * 1024), "40.0 MiB");
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_secs(42)), "42s");
+        assert_eq!(format_duration(Duration::from_secs(723)), "12m 3s");
+        assert_eq!(format_duration(Duration::from_secs(3900)), "1h 5m");
+    }
+
     #[test]
     fn test_write_file() -> Result<()> {
         let dir = tempfile::tempdir_in(".")?;